@@ -0,0 +1,41 @@
+//! Builds a small scene through the library API and renders it.
+//!
+//! ```text
+//! cargo run --release --example render_scene -- spheres.png
+//! ```
+
+use raytracer::{
+    BvhNode, CameraBuilder, Color, Dielectric, HittableList, Lambertian, Metal, Point3, Sphere,
+};
+use std::sync::Arc;
+
+fn main() {
+    let output = std::env::args().nth(1).unwrap_or_else(|| "spheres.png".to_string());
+
+    let ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let center = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+    let left = Arc::new(Dielectric::new(1.5));
+    let right = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.3));
+
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, ground)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.2), 0.5, center)));
+    world.add(Arc::new(Sphere::new(Point3::new(-1.0, 0.0, -1.0), 0.5, left)));
+    world.add(Arc::new(Sphere::new(Point3::new(1.0, 0.0, -1.0), 0.5, right)));
+    let world = BvhNode::from_list(&world);
+
+    let camera = CameraBuilder::new()
+        .image_width(400)
+        .samples_per_pixel(50)
+        .vfov(90.0)
+        .lookfrom(Point3::new(0.0, 0.0, 0.0))
+        .lookat(Point3::new(0.0, 0.0, -1.0))
+        .defocus_angle(0.0)
+        .build()
+        .expect("camera settings are valid");
+
+    if let Err(err) = camera.render(&world).save(&output) {
+        eprintln!("error: could not write {output}: {err}");
+        std::process::exit(1);
+    }
+}
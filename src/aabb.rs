@@ -0,0 +1,95 @@
+use crate::{interval::Interval, ray::Ray, vec3::Point3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+/// Boxes are never thinner than this along any axis, so flat primitives
+/// such as an axis-aligned quad still have a volume rays can hit.
+const MIN_THICKNESS: f64 = 0.0001;
+
+impl Aabb {
+    pub const fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        Aabb {
+            x: pad_to_minimum(x),
+            y: pad_to_minimum(y),
+            z: pad_to_minimum(z),
+        }
+    }
+
+    pub fn from_points(a: Point3, b: Point3) -> Self {
+        Aabb::new(
+            Interval::new(a.x().min(b.x()), a.x().max(b.x())),
+            Interval::new(a.y().min(b.y()), a.y().max(b.y())),
+            Interval::new(a.z().min(b.z()), a.z().max(b.z())),
+        )
+    }
+
+    pub const fn from_boxes(a: &Aabb, b: &Aabb) -> Self {
+        Aabb {
+            x: Interval::enclosing(a.x, b.x),
+            y: Interval::enclosing(a.y, b.y),
+            z: Interval::enclosing(a.z, b.z),
+        }
+    }
+
+    pub const fn empty() -> Self {
+        Aabb::new(Interval::empty(), Interval::empty(), Interval::empty())
+    }
+
+    pub const fn axis_interval(&self, n: usize) -> Interval {
+        match n {
+            1 => self.y,
+            2 => self.z,
+            _ => self.x,
+        }
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        if self.x.size() > self.y.size() {
+            if self.x.size() > self.z.size() { 0 } else { 2 }
+        } else if self.y.size() > self.z.size() {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn hit(&self, r: &Ray, mut ray_t: Interval) -> bool {
+        let ray_orig = r.origin();
+        let ray_dir = r.direction();
+
+        for axis in 0..3 {
+            let ax = self.axis_interval(axis);
+            let adinv = 1.0 / ray_dir[axis];
+
+            let t0 = (ax.min - ray_orig[axis]) * adinv;
+            let t1 = (ax.max - ray_orig[axis]) * adinv;
+
+            let (near, far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+            if near > ray_t.min {
+                ray_t.min = near;
+            }
+            if far < ray_t.max {
+                ray_t.max = far;
+            }
+
+            if ray_t.max <= ray_t.min {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+const fn pad_to_minimum(interval: Interval) -> Interval {
+    if interval.size() < MIN_THICKNESS {
+        interval.expand(MIN_THICKNESS)
+    } else {
+        interval
+    }
+}
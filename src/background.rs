@@ -0,0 +1,56 @@
+use crate::color::Color;
+use crate::environment::EnvironmentMap;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::sky::Daylight;
+use crate::vec3::{unit_vector, Vec3};
+use std::sync::Arc;
+
+/// Radiance arriving along rays that escape the scene.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Background {
+    /// The white-to-blue vertical gradient used by the default scenes.
+    #[default]
+    Sky,
+    /// The same color in every direction. Black gives a scene that is lit
+    /// only by emissive materials.
+    Solid(Color),
+    /// An image of the surroundings, sampled directly as a light.
+    Environment(Arc<EnvironmentMap>),
+    /// An analytic clear sky with a sun disk, sampled directly as a light.
+    Daylight(Arc<Daylight>),
+}
+
+impl Background {
+    pub fn value(&self, r: &Ray) -> Color {
+        match self {
+            Background::Sky => {
+                let unit_direction = unit_vector(r.direction());
+                let a = (unit_direction.y() + 1.0) * 0.5;
+                Color::new(1.0, 1.0, 1.0) * (1.0 - a) + Color::new(0.5, 0.7, 1.0) * a
+            }
+            Background::Solid(color) => *color,
+            Background::Environment(map) => map.value(r.direction()),
+            Background::Daylight(sky) => sky.value(r.direction()),
+        }
+    }
+
+    /// Solid angle density of [`Background::random`] producing `direction`.
+    pub fn pdf_value(&self, direction: Vec3) -> f64 {
+        match self {
+            Background::Environment(map) => map.pdf_value(direction),
+            Background::Daylight(sky) => sky.pdf_value(direction),
+            _ => 0.0,
+        }
+    }
+
+    /// A direction to sample the background along as a light, or `None` if
+    /// it is smooth enough that BSDF sampling alone finds it well.
+    pub fn random(&self, sampler: &mut Sampler) -> Option<Vec3> {
+        match self {
+            Background::Environment(map) => Some(map.random(sampler)),
+            Background::Daylight(sky) => Some(sky.random(sampler)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable, HittableList},
+    interval::Interval,
+    ray::Ray,
+    sampler::Sampler,
+};
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+pub struct BvhNode {
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    pub fn from_list(list: &HittableList) -> Self {
+        let mut objects = list.objects().to_vec();
+        Self::new(&mut objects)
+    }
+
+    pub fn new(objects: &mut [Arc<dyn Hittable>]) -> Self {
+        let bbox = objects
+            .iter()
+            .fold(Aabb::empty(), |bbox, object| {
+                Aabb::from_boxes(&bbox, &object.bounding_box())
+            });
+
+        let (left, right): (Arc<dyn Hittable>, Arc<dyn Hittable>) = match objects {
+            [] => {
+                let empty: Arc<dyn Hittable> = Arc::new(HittableList::new());
+                (empty.clone(), empty)
+            }
+            [only] => (only.clone(), only.clone()),
+            [first, second] => (first.clone(), second.clone()),
+            _ => {
+                let axis = bbox.longest_axis();
+                objects.sort_by(|a, b| box_compare(a, b, axis));
+
+                let mid = objects.len() / 2;
+                let (lower, upper) = objects.split_at_mut(mid);
+                (Arc::new(BvhNode::new(lower)), Arc::new(BvhNode::new(upper)))
+            }
+        };
+
+        BvhNode { left, right, bbox }
+    }
+}
+
+fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis: usize) -> Ordering {
+    let a_axis_interval = a.bounding_box().axis_interval(axis);
+    let b_axis_interval = b.bounding_box().axis_interval(axis);
+    a_axis_interval.min.total_cmp(&b_axis_interval.min)
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        if !self.bbox.hit(r, ray_t) {
+            return None;
+        }
+
+        let hit_left = self.left.hit(r, ray_t);
+        let right_max = hit_left.as_ref().map_or(ray_t.max, |rec| rec.t);
+        let hit_right = self.right.hit(r, Interval::new(ray_t.min, right_max));
+
+        hit_right.or(hit_left)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn shadow_hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        if !self.bbox.hit(r, ray_t) {
+            return None;
+        }
+
+        let hit_left = self.left.shadow_hit(r, ray_t);
+        let right_max = hit_left.as_ref().map_or(ray_t.max, |rec| rec.t);
+        let hit_right = self.right.shadow_hit(r, Interval::new(ray_t.min, right_max));
+
+        hit_right.or(hit_left)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval, sampler: &mut Sampler) -> f64 {
+        if !self.bbox.hit(r, ray_t) {
+            return 1.0;
+        }
+
+        let left = self.left.transmittance(r, ray_t, sampler);
+        // A leaf holding a single object has it on both sides.
+        if Arc::ptr_eq(&self.left, &self.right) {
+            return left;
+        }
+        left * self.right.transmittance(r, ray_t, sampler)
+    }
+
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        let mut emitters = HittableList::new();
+        emitters.add(self.left.clone());
+        if !Arc::ptr_eq(&self.left, &self.right) {
+            emitters.add(self.right.clone());
+        }
+        emitters.emitters()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::{Lambertian, Material, Metal};
+    use crate::sphere::Sphere;
+    use crate::vec3::{Point3, Vec3};
+
+    fn first_hit(world: &dyn Hittable, r: &Ray) -> Option<(f64, Point3, Vec3, bool)> {
+        world
+            .hit(r, Interval::new(0.001, f64::INFINITY))
+            .map(|rec| (rec.t, rec.p, rec.normal, rec.front_face))
+    }
+
+    #[test]
+    fn hits_match_the_linear_list() {
+        let mut world = HittableList::new();
+        let matte = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let metal = Arc::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.2));
+        world.add(Arc::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, matte.clone())));
+        for i in 0..40 {
+            // Scatter the spheres over overlapping grid cells so the tree has
+            // to split boxes that intersect.
+            let x = (i % 8) as f64 * 1.5 - 5.0 + 0.37 * (i % 3) as f64;
+            let z = (i / 8) as f64 * 1.5 - 3.0 + 0.29 * (i % 5) as f64;
+            let mat: Arc<dyn Material> = if i % 2 == 0 { matte.clone() } else { metal.clone() };
+            world.add(Arc::new(Sphere::new(Point3::new(x, 0.4, z), 0.4 + 0.02 * i as f64, mat)));
+        }
+
+        let bvh = BvhNode::from_list(&world);
+        assert_eq!(bvh.bounding_box(), world.bounding_box());
+
+        // Fire a fan of rays through the scene, as a camera would, and
+        // require the same closest hit from both.
+        let origin = Point3::new(0.0, 3.0, 12.0);
+        let mut hits = 0;
+        for j in 0..48 {
+            for i in 0..64 {
+                let target = Point3::new(i as f64 * 0.25 - 8.0, 2.0 - j as f64 * 0.125, 0.0);
+                let r = Ray::new(origin, target - origin);
+                let expected = first_hit(&world, &r);
+                hits += expected.is_some() as usize;
+                assert_eq!(first_hit(&bvh, &r), expected);
+            }
+        }
+        assert!(hits > 1000);
+    }
+
+    #[test]
+    fn empty_list_hits_nothing() {
+        let bvh = BvhNode::from_list(&HittableList::new());
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(bvh.hit(&r, Interval::universe()).is_none());
+    }
+}
@@ -1,93 +1,300 @@
-use crate::color::{write_color, Color};
-use crate::hit::Hittable;
+use crate::background::Background;
+use crate::color::Color;
+use crate::hit::{HitRecord, Hittable};
+use crate::image::Framebuffer;
 use crate::interval::Interval;
+use crate::light::LightList;
+use crate::material::ScatterResult;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{cross, random_in_unit_disk, unit_vector, Point3, Vec3};
 use indicatif::{ProgressBar, ProgressStyle};
-use rand::Rng;
+use std::fmt;
+use std::sync::Mutex;
+use std::thread;
 
-pub struct Camera {
-    pub aspect_ratio: f64,
-    pub image_width: u64,
-    image_height: u64,
-    center: Point3,
-    pixel00_loc: Point3,
-    pixel_delta_u: Vec3,
-    pixel_delta_v: Vec3,
-    defocus_disk_u: Vec3,
-    defocus_disk_v: Vec3,
-    u: Vec3,
-    v: Vec3,
-    w: Vec3,
+/// Image width used when neither the width nor the height is set.
+const DEFAULT_IMAGE_WIDTH: u64 = 1200;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CameraError {
+    ZeroImageSize,
+    InvalidAspectRatio(f64),
+    ZeroSamples,
+    ZeroThreads,
+    InvalidFieldOfView(f64),
+    InvalidDefocusAngle(f64),
+    InvalidFocusDistance(f64),
+    InvalidShutter(f64, f64),
+    DegenerateView,
+    DegenerateUp,
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CameraError::ZeroImageSize => write!(f, "image width and height must be at least 1"),
+            CameraError::InvalidAspectRatio(r) => {
+                write!(f, "aspect ratio must be positive, got {r}")
+            }
+            CameraError::ZeroSamples => write!(f, "samples per pixel must be at least 1"),
+            CameraError::ZeroThreads => write!(f, "thread count must be at least 1"),
+            CameraError::InvalidFieldOfView(vfov) => write!(
+                f,
+                "vertical field of view must be between 0 and 180 degrees, got {vfov}"
+            ),
+            CameraError::InvalidDefocusAngle(angle) => write!(
+                f,
+                "defocus angle must be at least 0 and less than 180 degrees, got {angle}"
+            ),
+            CameraError::InvalidFocusDistance(dist) => {
+                write!(f, "focus distance must be positive, got {dist}")
+            }
+            CameraError::InvalidShutter(open, close) => write!(
+                f,
+                "shutter must close at or after it opens, got {open} to {close}"
+            ),
+            CameraError::DegenerateView => write!(f, "lookfrom and lookat must be different points"),
+            CameraError::DegenerateUp => write!(
+                f,
+                "vup must be non-zero and not parallel to the viewing direction"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CameraError {}
+
+/// Collects camera settings and derives the image size, viewport and
+/// defocus disk from them in [`CameraBuilder::build`].
+///
+/// The image size comes from whichever of `image_width` and `image_height`
+/// are set; a missing dimension is derived from `aspect_ratio`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraBuilder {
+    aspect_ratio: f64,
+    image_width: Option<u64>,
+    image_height: Option<u64>,
     samples_per_pixel: u64,
-    pixel_samples_scale: f64,
     max_depth: u64,
+    min_depth: u64,
     vfov: f64,
     lookfrom: Point3,
     lookat: Point3,
     vup: Vec3,
     defocus_angle: f64,
     focus_dist: f64,
-    progress: ProgressBar,
+    shutter_open: f64,
+    shutter_close: f64,
+    threads: usize,
+    seed: u64,
+    background: Background,
 }
 
-impl Default for Camera {
+impl Default for CameraBuilder {
     fn default() -> Self {
-        let aspect_ratio = 16.0 / 9.0;
-        let image_width = 1200;
-        let samples_per_pixel = 500;
-        let max_depth = 50;
-        let vfov = 20.0;
-        let lookfrom = Point3::new(13.0, 2.0, 3.0);
-        let lookat = Point3::new(0.0, 0.0, 0.0);
-        let vup = Vec3::new(0.0, 1.0, 0.0);
-        let defocus_angle = 0.6;
-        let focus_dist = 10.0;
-        let center = lookfrom;
-        Self::new(
-            aspect_ratio,
-            image_width,
-            center,
-            samples_per_pixel,
-            max_depth,
-            vfov,
-            lookfrom,
-            lookat,
-            vup,
-            defocus_angle,
-            focus_dist,
-        )
+        CameraBuilder {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: None,
+            image_height: None,
+            samples_per_pixel: 500,
+            max_depth: 50,
+            min_depth: 5,
+            vfov: 20.0,
+            lookfrom: Point3::new(13.0, 2.0, 3.0),
+            lookat: Point3::new(0.0, 0.0, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            defocus_angle: 0.6,
+            focus_dist: 10.0,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: 0,
+            background: Background::default(),
+        }
     }
 }
 
-impl Camera {
-    fn new(
-        aspect_ratio: f64,
-        image_width: u64,
-        center: Point3,
-        samples_per_pixel: u64,
-        max_depth: u64,
-        vfov: f64,
-        lookfrom: Point3,
-        lookat: Point3,
-        vup: Vec3,
-        defocus_angle: f64,
-        focus_dist: f64,
-    ) -> Self {
-        let mut image_height = (image_width as f64 / aspect_ratio) as u64;
-        if image_height < 1 {
-            image_height = 1;
+impl CameraBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    pub fn image_width(mut self, image_width: u64) -> Self {
+        self.image_width = Some(image_width);
+        self
+    }
+
+    pub fn image_height(mut self, image_height: u64) -> Self {
+        self.image_height = Some(image_height);
+        self
+    }
+
+    pub fn samples_per_pixel(mut self, samples_per_pixel: u64) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: u64) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Bounces every path makes before Russian roulette may end it. After
+    /// that, a path survives each bounce with probability equal to its
+    /// brightest throughput channel and is scaled up to make up for the
+    /// paths that were ended, so dim paths stop early without biasing the
+    /// image. `max_depth` still caps the length of every path.
+    pub fn min_depth(mut self, min_depth: u64) -> Self {
+        self.min_depth = min_depth;
+        self
+    }
+
+    /// Vertical field of view in degrees.
+    pub fn vfov(mut self, vfov: f64) -> Self {
+        self.vfov = vfov;
+        self
+    }
+
+    pub fn lookfrom(mut self, lookfrom: Point3) -> Self {
+        self.lookfrom = lookfrom;
+        self
+    }
+
+    pub fn lookat(mut self, lookat: Point3) -> Self {
+        self.lookat = lookat;
+        self
+    }
+
+    pub fn vup(mut self, vup: Vec3) -> Self {
+        self.vup = vup;
+        self
+    }
+
+    /// Angle in degrees of the cone from each pixel to the defocus disk; zero
+    /// gives a pinhole camera with everything in focus.
+    pub fn defocus_angle(mut self, defocus_angle: f64) -> Self {
+        self.defocus_angle = defocus_angle;
+        self
+    }
+
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = focus_dist;
+        self
+    }
+
+    /// Time the shutter opens. Each ray is sent at a uniformly random time
+    /// while it is open, which blurs anything that moves in the meantime.
+    pub fn shutter_open(mut self, shutter_open: f64) -> Self {
+        self.shutter_open = shutter_open;
+        self
+    }
+
+    pub fn shutter_close(mut self, shutter_close: f64) -> Self {
+        self.shutter_close = shutter_close;
+        self
+    }
+
+    /// The interval the shutter is open, which moving objects should span.
+    pub fn shutter(&self) -> Interval {
+        Interval::new(self.shutter_open, self.shutter_close)
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Seed for every random decision made while rendering. The same seed
+    /// and scene always produce the same image.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
+    fn image_size(&self) -> Result<(u64, u64), CameraError> {
+        if !(self.aspect_ratio > 0.0 && self.aspect_ratio.is_finite()) {
+            return Err(CameraError::InvalidAspectRatio(self.aspect_ratio));
         }
 
-        let pixel_samples_scale = 1.0 / (samples_per_pixel as f64);
+        let (width, height) = match (self.image_width, self.image_height) {
+            (Some(width), Some(height)) => (width, height),
+            (Some(width), None) => (width, ((width as f64 / self.aspect_ratio) as u64).max(1)),
+            (None, Some(height)) => {
+                let width = (height as f64 * self.aspect_ratio).round() as u64;
+                (width.max(1), height)
+            }
+            (None, None) => {
+                let height = (DEFAULT_IMAGE_WIDTH as f64 / self.aspect_ratio) as u64;
+                (DEFAULT_IMAGE_WIDTH, height.max(1))
+            }
+        };
+
+        if width == 0 || height == 0 {
+            return Err(CameraError::ZeroImageSize);
+        }
+        Ok((width, height))
+    }
+
+    /// Checks the settings, returning the error [`CameraBuilder::build`]
+    /// would, without building a camera.
+    pub fn validate(&self) -> Result<(), CameraError> {
+        self.image_size()?;
+
+        if self.samples_per_pixel == 0 {
+            return Err(CameraError::ZeroSamples);
+        }
+        if self.threads == 0 {
+            return Err(CameraError::ZeroThreads);
+        }
+        if !(self.vfov > 0.0 && self.vfov < 180.0) {
+            return Err(CameraError::InvalidFieldOfView(self.vfov));
+        }
+        if !(0.0..180.0).contains(&self.defocus_angle) {
+            return Err(CameraError::InvalidDefocusAngle(self.defocus_angle));
+        }
+        if !(self.focus_dist > 0.0 && self.focus_dist.is_finite()) {
+            return Err(CameraError::InvalidFocusDistance(self.focus_dist));
+        }
+        if !(self.shutter_open.is_finite()
+            && self.shutter_close.is_finite()
+            && self.shutter_open <= self.shutter_close)
+        {
+            return Err(CameraError::InvalidShutter(self.shutter_open, self.shutter_close));
+        }
+        if (self.lookfrom - self.lookat).near_zero() {
+            return Err(CameraError::DegenerateView);
+        }
+        if cross(self.vup, self.lookfrom - self.lookat).near_zero() {
+            return Err(CameraError::DegenerateUp);
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> Result<Camera, CameraError> {
+        self.validate()?;
+        let (image_width, image_height) = self.image_size()?;
+
+        let center = self.lookfrom;
+        let pixel_samples_scale = 1.0 / (self.samples_per_pixel as f64);
 
-        let theta = vfov.to_radians();
+        let theta = self.vfov.to_radians();
         let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * focus_dist;
+        let viewport_height = 2.0 * h * self.focus_dist;
         let viewport_width = viewport_height * (image_width as f64 / image_height as f64);
 
-        let w = unit_vector(lookfrom - lookat);
-        let u = unit_vector(cross(vup, w));
+        let w = unit_vector(self.lookfrom - self.lookat);
+        let u = unit_vector(cross(self.vup, w));
         let v = cross(w, u);
 
         let viewport_u = u * viewport_width;
@@ -96,68 +303,161 @@ impl Camera {
         let pixel_delta_u = viewport_u / (image_width as f64);
         let pixel_delta_v = viewport_v / (image_height as f64);
 
-        let viewport_upper_left = center - (w * focus_dist) - viewport_u / 2.0 - viewport_v / 2.0;
+        let viewport_upper_left =
+            center - (w * self.focus_dist) - viewport_u / 2.0 - viewport_v / 2.0;
         let pixel00_loc = viewport_upper_left + (pixel_delta_u + pixel_delta_v) * 0.5;
 
-        let defocus_radius = focus_dist * (defocus_angle / 2.0).to_radians().tan();
+        let defocus_radius = self.focus_dist * (self.defocus_angle / 2.0).to_radians().tan();
         let defocus_disk_u = u * defocus_radius;
         let defocus_disk_v = v * defocus_radius;
 
-        let progress = ProgressBar::new(image_height);
-        progress.set_style(
-            ProgressStyle::with_template(
-                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len}",
-            )
-            .unwrap()
-            .progress_chars("#>-"),
-        );
-
-        Self {
-            aspect_ratio,
+        Ok(Camera {
             image_width,
             image_height,
+            threads: self.threads,
+            seed: self.seed,
+            background: self.background.clone(),
             center,
             pixel00_loc,
             pixel_delta_u,
             pixel_delta_v,
             defocus_disk_u,
             defocus_disk_v,
-            u,
-            v,
-            w,
-            samples_per_pixel,
+            samples_per_pixel: self.samples_per_pixel,
             pixel_samples_scale,
-            max_depth,
-            vfov,
-            lookfrom,
-            lookat,
-            vup,
-            defocus_angle,
-            focus_dist,
-            progress,
-        }
-    }
-
-    pub fn render(&mut self, world: &dyn Hittable) {
-        println!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-
-        for j in 0..self.image_height {
-            self.progress.inc(1);
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                for _sample in 0..self.samples_per_pixel {
-                    let r = self.get_ray(i, j);
-                    pixel_color += self.ray_color(&r, self.max_depth, world);
+            max_depth: self.max_depth,
+            min_depth: self.min_depth,
+            defocus_angle: self.defocus_angle,
+            shutter_open: self.shutter_open,
+            shutter_close: self.shutter_close,
+        })
+    }
+}
+
+/// A fully configured camera. Create one with [`CameraBuilder`]; every
+/// derived quantity is computed once when it is built.
+pub struct Camera {
+    image_width: u64,
+    image_height: u64,
+    threads: usize,
+    seed: u64,
+    background: Background,
+    center: Point3,
+    pixel00_loc: Point3,
+    pixel_delta_u: Vec3,
+    pixel_delta_v: Vec3,
+    defocus_disk_u: Vec3,
+    defocus_disk_v: Vec3,
+    samples_per_pixel: u64,
+    pixel_samples_scale: f64,
+    max_depth: u64,
+    min_depth: u64,
+    defocus_angle: f64,
+    shutter_open: f64,
+    shutter_close: f64,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        CameraBuilder::default()
+            .build()
+            .expect("default camera settings are valid")
+    }
+}
+
+impl Camera {
+    pub fn builder() -> CameraBuilder {
+        CameraBuilder::new()
+    }
+
+    pub fn image_width(&self) -> u64 {
+        self.image_width
+    }
+
+    pub fn image_height(&self) -> u64 {
+        self.image_height
+    }
+
+    pub fn samples_per_pixel(&self) -> u64 {
+        self.samples_per_pixel
+    }
+
+    pub fn max_depth(&self) -> u64 {
+        self.max_depth
+    }
+
+    pub fn min_depth(&self) -> u64 {
+        self.min_depth
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn render(&self, world: &dyn Hittable) -> Framebuffer {
+        self.render_with_lights(world, &LightList::new())
+    }
+
+    /// Renders `world`, sampling `lights` directly at every diffuse bounce.
+    /// Emissive surfaces are found far sooner this way than by
+    /// paths that only hit them by chance, which matters most for small,
+    /// bright lights, and punctual lights can only be found this way.
+    pub fn render_with_lights(&self, world: &dyn Hittable, lights: &LightList) -> Framebuffer {
+        let height = self.image_height as usize;
+        let mut image = Framebuffer::new(self.image_width as usize, height);
+        let progress = ProgressBar::new(self.image_height);
+        progress.set_style(
+            ProgressStyle::with_template(
+                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len}",
+            )
+            .unwrap()
+            .progress_chars("#>-"),
+        );
+
+        // Workers pull whole scanlines off a shared queue and write them
+        // straight into their slot of the framebuffer, so the output order
+        // never depends on which thread rendered which row.
+        {
+            let rows = Mutex::new(image.rows_mut().enumerate());
+            let threads = self.threads.clamp(1, height.max(1));
+
+            thread::scope(|s| {
+                for _ in 0..threads {
+                    s.spawn(|| {
+                        loop {
+                            let Some((j, row)) = rows.lock().unwrap().next() else {
+                                break;
+                            };
+                            self.render_row(j as u64, row, world, lights);
+                            progress.inc(1);
+                        }
+                    });
                 }
-                write_color(&(pixel_color * self.pixel_samples_scale));
-            }
+            });
         }
 
-        self.progress.finish();
+        progress.finish();
+        image
+    }
+
+    fn render_row(&self, j: u64, row: &mut [Color], world: &dyn Hittable, lights: &LightList) {
+        for (i, pixel) in row.iter_mut().enumerate() {
+            let mut pixel_color = Color::new(0.0, 0.0, 0.0);
+            for sample in 0..self.samples_per_pixel {
+                let mut sampler = Sampler::for_sample(self.seed, i as u64, j, sample);
+                let r = self.get_ray(i as u64, j, &mut sampler);
+                pixel_color += self.ray_color(r, world, lights, &mut sampler);
+            }
+            *pixel = pixel_color * self.pixel_samples_scale;
+        }
     }
 
-    fn get_ray(&self, i: u64, j: u64) -> Ray {
-        let offset = self.sample_square();
+    fn get_ray(&self, i: u64, j: u64, sampler: &mut Sampler) -> Ray {
+        let offset = self.sample_square(sampler);
         let pixel_sample = self.pixel00_loc
             + (self.pixel_delta_u * (i as f64 + offset.x()))
             + (self.pixel_delta_v * (j as f64 + offset.y()));
@@ -165,42 +465,634 @@ impl Camera {
         let ray_origin = if self.defocus_angle <= 0.0 {
             self.center
         } else {
-            self.defocus_disk_sample()
+            self.defocus_disk_sample(sampler)
         };
         let ray_direction = pixel_sample - ray_origin;
+        let ray_time = sampler.random_range(self.shutter_open, self.shutter_close);
 
-        Ray::new(ray_origin, ray_direction)
+        Ray::with_time(ray_origin, ray_direction, ray_time)
     }
 
-    fn defocus_disk_sample(&self) -> Point3 {
-        let p = random_in_unit_disk();
+    fn defocus_disk_sample(&self, sampler: &mut Sampler) -> Point3 {
+        let p = random_in_unit_disk(sampler);
         self.center + (p.x() * self.defocus_disk_u) + (p.y() * self.defocus_disk_v)
     }
 
-    fn sample_square(&self) -> Vec3 {
-        let mut rng = rand::rng();
+    fn sample_square(&self, sampler: &mut Sampler) -> Vec3 {
         Vec3::new(
-            rng.random_range(-0.5..0.5),
-            rng.random_range(-0.5..0.5),
+            sampler.random_range(-0.5, 0.5),
+            sampler.random_range(-0.5, 0.5),
             0.0,
         )
     }
 
-    fn ray_color(&self, r: &Ray, depth: u64, world: &dyn Hittable) -> Color {
-        if depth <= 0 {
-            return Color::new(0.0, 0.0, 0.0);
+    /// Radiance arriving along `r`, following one path of at most
+    /// `max_depth` rays from the camera.
+    ///
+    /// `throughput` is the product of the path's BSDF weights so far, and
+    /// `emission_weight` the multiple importance sampling weight of the
+    /// last BSDF sample against sampling `lights`, which applies to any
+    /// emission found at the end of the current ray. `background_weight` is
+    /// its weight against sampling the background instead, should the ray
+    /// escape.
+    fn ray_color(
+        &self,
+        mut r: Ray,
+        world: &dyn Hittable,
+        lights: &LightList,
+        sampler: &mut Sampler,
+    ) -> Color {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut emission_weight = 1.0;
+        let mut background_weight = 1.0;
+
+        for depth in 1..=self.max_depth {
+            let Some(rec) = world.hit(&r, Interval::new(0.001, f64::INFINITY)) else {
+                return color + throughput * background_weight * self.background.value(&r);
+            };
+
+            // Media have no surface to reflect from or emit: the phase
+            // function alone picks the new direction, continuing from the
+            // point inside.
+            if let Some(volume) = rec.mat.volume_scattering(&rec) {
+                let wo = unit_vector(r.direction());
+                let phase = |direction: Vec3| {
+                    let p = volume.phase.p(wo, direction);
+                    (volume.albedo * p, p)
+                };
+                let direct = self.direct_light(&rec, r.time(), world, lights, sampler, phase);
+                color += throughput * direct;
+
+                let direction = volume.phase.sample(wo, sampler);
+                let phase_pdf = volume.phase.p(wo, direction);
+                r = Ray::with_time(rec.p, direction, r.time());
+                emission_weight = bsdf_sample_weight(lights, &r, phase_pdf);
+                background_weight =
+                    power_heuristic(phase_pdf, self.background.pdf_value(direction));
+                throughput = throughput * volume.albedo;
+            } else {
+                color += throughput * emission_weight * rec.mat.emitted(&r, &rec);
+                let Some(scatter_result) = rec.mat.scatter(&r, &rec, sampler) else {
+                    return color;
+                };
+
+                match scatter_result {
+                    ScatterResult::Specular {
+                        attenuation,
+                        scattered,
+                    } => {
+                        throughput = throughput * attenuation;
+                        emission_weight = 1.0;
+                        background_weight = 1.0;
+                        r = scattered;
+                    }
+                    ScatterResult::Sampled { scattered, pdf } => {
+                        // The BSDF times the cosine, and the density the
+                        // material samples it with, for any direction
+                        // leaving the surface.
+                        let bsdf = |direction: Vec3| {
+                            let towards = Ray::with_time(rec.p, direction, r.time());
+                            (rec.mat.bsdf(&r, &rec, &towards), pdf.value(direction))
+                        };
+                        let direct = self.direct_light(&rec, r.time(), world, lights, sampler, bsdf);
+                        color += throughput * direct;
+
+                        let direction = scattered.direction();
+                        let (value, pdf_value) = bsdf(direction);
+                        if pdf_value <= 0.0 {
+                            return color;
+                        }
+                        emission_weight = bsdf_sample_weight(lights, &scattered, pdf_value);
+                        background_weight =
+                            power_heuristic(pdf_value, self.background.pdf_value(direction));
+                        throughput = throughput * value / pdf_value;
+                        r = scattered;
+                    }
+                }
+            }
+
+            if depth >= self.min_depth {
+                let survival = throughput.x().max(throughput.y()).max(throughput.z()).min(1.0);
+                if sampler.random() >= survival {
+                    return color;
+                }
+                throughput /= survival;
+            }
+        }
+
+        color
+    }
+
+    /// Light reaching `rec.p` directly from `lights`, weighted by
+    /// `bsdf(direction) = (value, pdf)`.
+    ///
+    /// Each punctual light contributes through one shadow ray. One direction
+    /// is sampled toward the area lights, and weighted by multiple
+    /// importance sampling against the BSDF's own sampling; its shadow ray
+    /// takes the emission of whatever it hits first. An environment map
+    /// background gets one such sample of its own, which counts only if the
+    /// shadow ray escapes. Either way an occluder blocks the light, and
+    /// shadow rays pass through media, which attenuate the light by their
+    /// transmittance.
+    fn direct_light(
+        &self,
+        rec: &HitRecord,
+        time: f64,
+        world: &dyn Hittable,
+        lights: &LightList,
+        sampler: &mut Sampler,
+        bsdf: impl Fn(Vec3) -> (Color, f64),
+    ) -> Color {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let mut color = black;
+
+        for light in lights.punctual() {
+            let Some(sample) = light.sample(rec.p) else {
+                continue;
+            };
+            let (value, _) = bsdf(sample.direction);
+            if value == black {
+                continue;
+            }
+            let shadow_ray = Ray::with_time(rec.p, sample.direction, time);
+            let span = Interval::new(0.001, sample.distance);
+            if world.shadow_hit(&shadow_ray, span).is_none() {
+                let transmittance = world.transmittance(&shadow_ray, span, sampler);
+                color += value * sample.irradiance * transmittance;
+            }
+        }
+
+        if let Some(direction) = self.background.random(sampler) {
+            let background_pdf = self.background.pdf_value(direction);
+            let (value, bsdf_pdf) = bsdf(direction);
+            if background_pdf > 0.0 && value != black {
+                let shadow_ray = Ray::with_time(rec.p, direction, time);
+                let span = Interval::new(0.001, f64::INFINITY);
+                if world.shadow_hit(&shadow_ray, span).is_none() {
+                    let weight = power_heuristic(background_pdf, bsdf_pdf) / background_pdf
+                        * world.transmittance(&shadow_ray, span, sampler);
+                    color += value * self.background.value(&shadow_ray) * weight;
+                }
+            }
+        }
+
+        let area = lights.area();
+        if area.objects().is_empty() {
+            return color;
+        }
+        let direction = area.random(rec.p, time, sampler);
+        let light_pdf = area.pdf_value(rec.p, direction, time);
+        if light_pdf <= 0.0 {
+            return color;
+        }
+        let (value, bsdf_pdf) = bsdf(direction);
+        if value == black {
+            return color;
+        }
+
+        let shadow_ray = Ray::with_time(rec.p, direction, time);
+        let Some(light_rec) = world.shadow_hit(&shadow_ray, Interval::new(0.001, f64::INFINITY))
+        else {
+            return color;
+        };
+        let emitted = light_rec.mat.emitted(&shadow_ray, &light_rec)
+            * world.transmittance(&shadow_ray, Interval::new(0.001, light_rec.t), sampler);
+        color + value * emitted * power_heuristic(light_pdf, bsdf_pdf) / light_pdf
+    }
+}
+
+/// The weight of a BSDF-sampled ray drawn with density `bsdf_pdf`, given
+/// that [`Camera::direct_light`] could also have sampled it.
+fn bsdf_sample_weight(lights: &LightList, scattered: &Ray, bsdf_pdf: f64) -> f64 {
+    let area = lights.area();
+    if area.objects().is_empty() {
+        return 1.0;
+    }
+    let light_pdf = area.pdf_value(scattered.origin(), scattered.direction(), scattered.time());
+    power_heuristic(bsdf_pdf, light_pdf)
+}
+
+/// Veach's power heuristic with exponent 2: the weight of a sample drawn
+/// with density `pdf` when another strategy has density `other_pdf` for it.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 { a / (a + b) } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::EnvironmentMap;
+    use crate::hit::HittableList;
+    use crate::light::{DirectionalLight, PointLight};
+    use crate::material::{
+        Anisotropic, ComplexIor, Conductor, Dielectric, DiffuseLight, Lambertian, Metal,
+    };
+    use crate::medium::ConstantMedium;
+    use crate::quad::Quad;
+    use crate::sky::Daylight;
+    use crate::sphere::Sphere;
+    use crate::vec3::dot;
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+
+    fn small_scene() -> HittableList {
+        let mut world = HittableList::new();
+        let ground = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let glass = Arc::new(Dielectric::new(1.5));
+        world.add(Arc::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground)));
+        world.add(Arc::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, glass)));
+        world
+    }
+
+    /// Renders `object` alone against a uniform `sky`, in a 9x9 view of the
+    /// origin from 10 units away.
+    fn render_in_uniform_sky(
+        object: Arc<dyn Hittable>,
+        sky: Color,
+        samples_per_pixel: u64,
+        vfov: f64,
+    ) -> Framebuffer {
+        let mut world = HittableList::new();
+        world.add(object);
+        CameraBuilder::new()
+            .image_width(9)
+            .image_height(9)
+            .samples_per_pixel(samples_per_pixel)
+            .lookfrom(Point3::new(0.0, 0.0, 10.0))
+            .lookat(Point3::new(0.0, 0.0, 0.0))
+            .vfov(vfov)
+            .defocus_angle(0.0)
+            .background(Background::Solid(sky))
+            .build()
+            .unwrap()
+            .render(&world)
+    }
+
+    /// Looks straight down at the origin of [`matte_plane`] against a black
+    /// background, so its center pixel sees only the direct light there.
+    fn overhead_camera() -> Camera {
+        CameraBuilder::new()
+            .image_width(3)
+            .image_height(3)
+            .samples_per_pixel(1)
+            .max_depth(1)
+            .lookfrom(Point3::new(0.0, 10.0, 0.01))
+            .lookat(Point3::new(0.0, 0.0, 0.0))
+            .vfov(0.01)
+            .defocus_angle(0.0)
+            .background(Background::Solid(Color::new(0.0, 0.0, 0.0)))
+            .build()
+            .unwrap()
+    }
+
+    /// A matte plane through the origin, facing up, wide enough to fill the
+    /// view of [`plane_camera`].
+    fn matte_plane() -> HittableList {
+        let mut world = HittableList::new();
+        let matte = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        world.add(Arc::new(Quad::new(
+            Point3::new(-100.0, 0.0, -100.0),
+            Vec3::new(0.0, 0.0, 200.0),
+            Vec3::new(200.0, 0.0, 0.0),
+            matte,
+        )));
+        world
+    }
+
+    /// A tiny 4x4 view of the plane around the origin, from low down.
+    fn plane_camera(samples_per_pixel: u64, background: Background) -> Camera {
+        CameraBuilder::new()
+            .image_width(4)
+            .image_height(4)
+            .samples_per_pixel(samples_per_pixel)
+            .lookfrom(Point3::new(10.0, 1.0, 0.0))
+            .lookat(Point3::new(0.0, 0.0, 0.0))
+            .vfov(0.5)
+            .defocus_angle(0.0)
+            .background(background)
+            .build()
+            .unwrap()
+    }
+
+    fn mean(image: &Framebuffer) -> Color {
+        let sum = image.pixels().iter().fold(Color::new(0.0, 0.0, 0.0), |sum, &c| sum + c);
+        sum / image.pixels().len() as f64
+    }
+
+    #[test]
+    fn output_does_not_depend_on_thread_count() {
+        let world = small_scene();
+        let builder = CameraBuilder::new().image_width(24).samples_per_pixel(3).seed(9);
+
+        let single = builder.clone().threads(1).build().unwrap().render(&world);
+        let multi = builder.clone().threads(5).build().unwrap().render(&world);
+        let reseeded = builder.seed(10).threads(1).build().unwrap().render(&world);
+
+        assert_eq!(single, multi);
+        assert_ne!(single, reseeded);
+    }
+
+    #[test]
+    fn emissive_surfaces_light_a_black_background() {
+        let mut world = HittableList::new();
+        let light = Arc::new(DiffuseLight::new(Color::new(4.0, 2.0, 1.0)));
+        world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, light)));
+
+        let image = CameraBuilder::new()
+            .image_width(9)
+            .image_height(9)
+            .samples_per_pixel(2)
+            .lookfrom(Point3::new(0.0, 0.0, 10.0))
+            .lookat(Point3::new(0.0, 0.0, 0.0))
+            .vfov(30.0)
+            .defocus_angle(0.0)
+            .background(Background::Solid(Color::new(0.0, 0.0, 0.0)))
+            .build()
+            .unwrap()
+            .render(&world);
+
+        assert_eq!(image.get(4, 4), Color::new(4.0, 2.0, 1.0));
+        assert_eq!(image.get(0, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn white_media_conserve_a_uniform_background() {
+        // Light that scatters without absorption inside a uniformly lit
+        // world must come out exactly as bright as it went in.
+        let unused = Arc::new(Lambertian::new(Color::new(0.0, 0.0, 0.0)));
+        let boundary = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, unused));
+        let fog = Arc::new(ConstantMedium::with_phase_function(
+            boundary,
+            1.0,
+            Arc::new(Anisotropic::new(Color::new(1.0, 1.0, 1.0), 0.5)),
+        ));
+
+        let sky = Color::new(0.25, 0.5, 1.0);
+        let image = render_in_uniform_sky(fog, sky, 4, 15.0);
+
+        assert!(image.pixels().iter().all(|&c| c == sky));
+    }
+
+    #[test]
+    fn cosine_sampled_white_surfaces_conserve_a_uniform_background() {
+        // The BRDF over the cosine pdf is exactly one for a white diffuser,
+        // so every path returns the sky unchanged, up to rounding.
+        let white = Arc::new(Lambertian::new(Color::new(1.0, 1.0, 1.0)));
+        let ball = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, white));
+
+        let sky = Color::new(0.25, 0.5, 1.0);
+        let image = render_in_uniform_sky(ball, sky, 4, 15.0);
+
+        assert!(image.pixels().iter().all(|&c| (c - sky).length() < 1e-9));
+    }
+
+    #[test]
+    fn rough_conductors_never_reflect_more_than_arrives() {
+        // With the visible normals sampled, each path's weight is F G / G1,
+        // which is at most one for a perfect reflector. What is lost is the
+        // light a single microfacet reflection sends into the surface or
+        // that other microfacets block.
+        let mirror = ComplexIor {
+            eta: Color::new(1.0, 1.0, 1.0),
+            k: Color::new(1e4, 1e4, 1e4),
+        };
+        for conductor in [Conductor::new(mirror, 0.5), Conductor::anisotropic(mirror, 0.2, 0.7)] {
+            let ball = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, Arc::new(conductor)));
+            let image = render_in_uniform_sky(ball, Color::new(1.0, 1.0, 1.0), 16, 11.0);
+
+            assert!(image.pixels().iter().all(|c| c.x() <= 1.0 + 1e-9));
+            let mean = mean(&image).x();
+            assert!(mean > 0.8, "{mean}");
         }
+    }
+
+    #[test]
+    fn sampling_a_small_light_matches_its_analytic_irradiance() {
+        // A sphere of radius 1 and radiance 25 whose center is 5 above a
+        // diffuse plane gives it irradiance pi * 25 / 25 = pi, so the plane
+        // reflects radiance albedo * irradiance / pi = 0.5 beneath it.
+        let mut world = matte_plane();
+        let mut lights = LightList::new();
+        let lamp = Arc::new(DiffuseLight::new(Color::new(25.0, 25.0, 25.0)));
+        let light = Arc::new(Sphere::new(Point3::new(0.0, 5.0, 0.0), 1.0, lamp));
+        world.add(light.clone());
+        lights.add_area(light);
+
+        let camera = plane_camera(64, Background::Solid(Color::new(0.0, 0.0, 0.0)));
+        let sampled = camera.render_with_lights(&world, &lights);
+        let sampled_mean = mean(&sampled).x();
+        assert!((sampled_mean - 0.5).abs() < 0.01, "{sampled_mean}");
+
+        // Without light sampling the estimate is unbiased but far noisier.
+        let unsampled = camera.render(&world);
+        let spread = |image: &Framebuffer| {
+            let m = mean(image).x();
+            image.pixels().iter().map(|c| (c.x() - m).powi(2)).sum::<f64>()
+        };
+        assert!(spread(&sampled) * 10.0 < spread(&unsampled));
+    }
+
+    #[test]
+    fn sampling_an_environment_map_matches_its_analytic_irradiance() {
+        // One pixel of an 8x4 map spans an eighth of the azimuth and the
+        // top quarter of the polar angle, so radiance 32 there gives a plane
+        // facing up irradiance 32 * pi / 16, and the plane reflects radiance
+        // 0.5 * 2 = 1.
+        let mut image = Framebuffer::new(8, 4);
+        image.set(5, 0, Color::new(32.0, 32.0, 32.0));
+        let map = EnvironmentMap::new(image).with_rotation(10.0);
+
+        let camera = plane_camera(256, Background::Environment(Arc::new(map)));
+        let mean = mean(&camera.render(&matte_plane())).x();
+        assert!((mean - 1.0).abs() < 0.02, "{mean}");
+    }
+
+    #[test]
+    fn daylight_sun_and_sky_light_a_matte_plane() {
+        let daylight = Daylight::new(60.0, 30.0, 3.0);
+        let sun = daylight.sun_direction();
+        let background = Background::Daylight(Arc::new(daylight.clone()));
 
-        if let Some(rec) = world.hit(r, Interval::new(0.001, f64::INFINITY)) {
-            if let Some(scatter_result) = rec.mat.scatter(r, &rec) {
-                return scatter_result.attenuation
-                    * self.ray_color(&scatter_result.scattered, depth - 1, world);
+        // Sun plus sky irradiance on the plane, integrating the sky by
+        // quadrature around everything but the sun disk.
+        let mut irradiance = daylight.sun_irradiance().y() * sun.y();
+        let (n_theta, n_phi) = (200, 400);
+        for i in 0..n_theta {
+            let theta = (i as f64 + 0.5) / n_theta as f64 * PI / 2.0;
+            for j in 0..n_phi {
+                let phi = (j as f64 + 0.5) / n_phi as f64 * 2.0 * PI;
+                let d = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                if dot(d, sun) < 1f64.to_radians().cos() {
+                    let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), d);
+                    let solid_angle = theta.sin() * (PI / 2.0 / n_theta as f64)
+                        * (2.0 * PI / n_phi as f64);
+                    irradiance += background.value(&ray).y() * theta.cos() * solid_angle;
+                }
             }
-            return Color::new(0.0, 0.0, 0.0);
         }
 
-        let unit_direction = unit_vector(r.direction());
-        let a = (unit_direction.y() + 1.0) * 0.5;
-        Color::new(1.0, 1.0, 1.0) * (1.0 - a) + Color::new(0.5, 0.7, 1.0) * a
+        let mean = mean(&plane_camera(256, background).render(&matte_plane())).y();
+        let expected = 0.5 * irradiance / PI;
+        assert!((mean / expected - 1.0).abs() < 0.02, "{mean} vs {expected}");
+    }
+
+    #[test]
+    fn punctual_lights_shade_and_cast_shadows() {
+        // A point light of intensity 4 two units above a diffuse plane gives
+        // irradiance 1 beneath it, which the plane reflects as 0.5 / pi.
+        // A sphere between the two blocks a directional light from the side.
+        let mut world = matte_plane();
+        let matte = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        world.add(Arc::new(Sphere::new(Point3::new(2.0, 1.0, 0.0), 0.5, matte)));
+        let camera = overhead_camera();
+
+        let mut lights = LightList::new();
+        lights.add(Arc::new(PointLight::new(Point3::new(0.0, 2.0, 0.0), Color::new(4.0, 4.0, 4.0))));
+        let lit = camera.render_with_lights(&world, &lights);
+        assert!((lit.get(1, 1).x() - 0.5 / PI).abs() < 1e-4);
+
+        let mut lights = LightList::new();
+        let sun = DirectionalLight::new(Vec3::new(-2.0, -1.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        lights.add(Arc::new(sun));
+        assert_eq!(camera.render_with_lights(&world, &lights).get(1, 1), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(camera.render(&world).get(1, 1), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn media_dim_the_lights_behind_them() {
+        // A shadow ray through the middle of a fog ball of radius 0.5 and
+        // density 1 carries exp(-1) of the light, by the Beer-Lambert law.
+        let mut world = matte_plane();
+        let camera = overhead_camera();
+        let mut lights = LightList::new();
+        let sun = DirectionalLight::new(Vec3::new(-2.0, -1.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        lights.add(Arc::new(sun));
+        let clear = camera.render_with_lights(&world, &lights).get(1, 1).x();
+
+        let matte = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let boundary = Arc::new(Sphere::new(Point3::new(2.0, 1.0, 0.0), 0.5, matte));
+        world.add(Arc::new(ConstantMedium::new(boundary, 1.0, Color::new(0.5, 0.5, 0.5))));
+        let foggy = camera.render_with_lights(&world, &lights).get(1, 1).x();
+
+        assert!(clear > 0.0);
+        assert!((foggy / clear - (-1.0f64).exp()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn russian_roulette_keeps_the_expected_brightness() {
+        // Grey fog scatters many times, so the paths run well past the
+        // minimum depth; ending some of them must not change the mean.
+        let mut world = HittableList::new();
+        let unused = Arc::new(Lambertian::new(Color::new(0.0, 0.0, 0.0)));
+        let boundary = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, unused));
+        world.add(Arc::new(ConstantMedium::new(boundary, 4.0, Color::new(0.8, 0.8, 0.8))));
+
+        let builder = CameraBuilder::new()
+            .image_width(8)
+            .image_height(8)
+            .samples_per_pixel(64)
+            .lookfrom(Point3::new(0.0, 0.0, 10.0))
+            .lookat(Point3::new(0.0, 0.0, 0.0))
+            .vfov(8.0)
+            .defocus_angle(0.0)
+            .background(Background::Solid(Color::new(1.0, 1.0, 1.0)));
+        let mean = |image: Framebuffer| {
+            image.pixels().iter().map(|c| c.x()).sum::<f64>() / image.pixels().len() as f64
+        };
+
+        let full = mean(builder.clone().min_depth(50).build().unwrap().render(&world));
+        let roulette = mean(builder.min_depth(0).build().unwrap().render(&world));
+        assert!(full < 0.9);
+        assert!((full - roulette).abs() < 0.02, "{full} vs {roulette}");
+    }
+
+    #[test]
+    fn very_deep_paths_do_not_overflow_the_stack() {
+        // Light bounces between two perfect mirrors until max_depth.
+        let mut world = HittableList::new();
+        let mirror = Arc::new(Metal::new(Color::new(1.0, 1.0, 1.0), 0.0));
+        for z in [-1.0, 1.0] {
+            world.add(Arc::new(Quad::new(
+                Point3::new(-500.0, -500.0, z),
+                Vec3::new(1000.0, 0.0, 0.0),
+                Vec3::new(0.0, 1000.0, 0.0),
+                mirror.clone(),
+            )));
+        }
+
+        let image = CameraBuilder::new()
+            .image_width(1)
+            .image_height(1)
+            .samples_per_pixel(1)
+            .max_depth(1_000_000)
+            .lookfrom(Point3::new(0.0, 0.0, 0.0))
+            .lookat(Point3::new(0.0, 0.0, -1.0))
+            .vfov(0.001)
+            .defocus_angle(0.0)
+            .build()
+            .unwrap()
+            .render(&world);
+        assert_eq!(image.get(0, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn derives_missing_image_dimension_from_aspect_ratio() {
+        let camera = CameraBuilder::new().build().unwrap();
+        assert_eq!((camera.image_width(), camera.image_height()), (1200, 675));
+
+        let camera = CameraBuilder::new().aspect_ratio(2.0).image_width(101).build().unwrap();
+        assert_eq!((camera.image_width(), camera.image_height()), (101, 50));
+
+        let camera = CameraBuilder::new().aspect_ratio(2.0).image_height(30).build().unwrap();
+        assert_eq!((camera.image_width(), camera.image_height()), (60, 30));
+
+        let camera = CameraBuilder::new().image_width(7).image_height(5).build().unwrap();
+        assert_eq!((camera.image_width(), camera.image_height()), (7, 5));
+    }
+
+    #[test]
+    fn rebuilding_after_a_change_recomputes_derived_state() {
+        let builder = CameraBuilder::new().image_width(100).samples_per_pixel(4);
+        let first = builder.build().unwrap();
+        let second = builder.clone().vfov(60.0).build().unwrap();
+
+        assert_eq!(first.pixel00_loc, builder.build().unwrap().pixel00_loc);
+        assert_ne!(first.pixel00_loc, second.pixel00_loc);
+        assert_eq!(second.pixel_samples_scale, 0.25);
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let err = |b: CameraBuilder| {
+            let err = b.validate().unwrap_err();
+            assert_eq!(b.build().err(), Some(err.clone()));
+            err
+        };
+        assert_eq!(CameraBuilder::new().validate(), Ok(()));
+        assert_eq!(err(CameraBuilder::new().image_width(0)), CameraError::ZeroImageSize);
+        assert_eq!(
+            err(CameraBuilder::new().aspect_ratio(-1.0)),
+            CameraError::InvalidAspectRatio(-1.0)
+        );
+        assert_eq!(err(CameraBuilder::new().samples_per_pixel(0)), CameraError::ZeroSamples);
+        assert_eq!(err(CameraBuilder::new().vfov(180.0)), CameraError::InvalidFieldOfView(180.0));
+        assert_eq!(
+            err(CameraBuilder::new().focus_dist(0.0)),
+            CameraError::InvalidFocusDistance(0.0)
+        );
+        assert_eq!(
+            err(CameraBuilder::new().shutter_open(1.0).shutter_close(0.5)),
+            CameraError::InvalidShutter(1.0, 0.5)
+        );
+        assert_eq!(
+            err(CameraBuilder::new().lookat(Point3::new(13.0, 2.0, 3.0))),
+            CameraError::DegenerateView
+        );
+        assert_eq!(
+            err(CameraBuilder::new().vup(Vec3::new(0.0, 0.0, 0.0))),
+            CameraError::DegenerateUp
+        );
     }
 }
@@ -0,0 +1,306 @@
+use raytracer::{CameraBuilder, ImageFormat, Point3, Vec3};
+use std::fmt;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: raytracer [OPTIONS]
+
+Options:
+  -o, --output <PATH>            Output image; the extension picks the format:
+                                 .png, .ppm, .p3, .hdr, .pfm or .exr [default: image.png]
+  -s, --scene <PATH>             JSON scene file [default: built-in random spheres]
+  -w, --width <PIXELS>           Image width
+  -H, --height <PIXELS>          Image height
+      --samples <N>              Samples per pixel
+      --max-depth <N>            Maximum number of ray bounces
+      --min-depth <N>            Bounces before Russian roulette may end a path
+      --seed <N>                 Random seed for the render and the built-in
+                                 random spheres scene [default: 0]
+  -j, --threads <N>              Render threads [default: one per CPU core]
+      --lookfrom <X,Y,Z>         Camera position
+      --lookat <X,Y,Z>           Point the camera looks at
+      --vup <X,Y,Z>              Camera up direction
+      --vfov <DEGREES>           Vertical field of view
+      --defocus-angle <DEGREES>  Aperture cone angle; 0 disables depth of field
+      --focus-dist <DISTANCE>    Distance to the plane of perfect focus
+  -h, --help                     Print this help
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CliError(String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Render(Box<Options>),
+    Help,
+}
+
+/// Render settings from the command line. Camera settings left as `None`
+/// keep the value from the scene file, or the built-in default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub output: PathBuf,
+    pub scene: Option<PathBuf>,
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+    pub samples: Option<u64>,
+    pub max_depth: Option<u64>,
+    pub min_depth: Option<u64>,
+    pub seed: u64,
+    pub threads: Option<usize>,
+    pub lookfrom: Option<Point3>,
+    pub lookat: Option<Point3>,
+    pub vup: Option<Vec3>,
+    pub vfov: Option<f64>,
+    pub defocus_angle: Option<f64>,
+    pub focus_dist: Option<f64>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            output: PathBuf::from("image.png"),
+            scene: None,
+            width: None,
+            height: None,
+            samples: None,
+            max_depth: None,
+            min_depth: None,
+            seed: 0,
+            threads: None,
+            lookfrom: None,
+            lookat: None,
+            vup: None,
+            vfov: None,
+            defocus_angle: None,
+            focus_dist: None,
+        }
+    }
+}
+
+impl Options {
+    /// Applies every camera override on top of `camera`.
+    pub fn configure_camera(&self, mut camera: CameraBuilder) -> CameraBuilder {
+        if let Some(width) = self.width {
+            camera = camera.image_width(width);
+        }
+        if let Some(height) = self.height {
+            camera = camera.image_height(height);
+        }
+        if let Some(samples) = self.samples {
+            camera = camera.samples_per_pixel(samples);
+        }
+        if let Some(max_depth) = self.max_depth {
+            camera = camera.max_depth(max_depth);
+        }
+        if let Some(min_depth) = self.min_depth {
+            camera = camera.min_depth(min_depth);
+        }
+        camera = camera.seed(self.seed);
+        if let Some(threads) = self.threads {
+            camera = camera.threads(threads);
+        }
+        if let Some(lookfrom) = self.lookfrom {
+            camera = camera.lookfrom(lookfrom);
+        }
+        if let Some(lookat) = self.lookat {
+            camera = camera.lookat(lookat);
+        }
+        if let Some(vup) = self.vup {
+            camera = camera.vup(vup);
+        }
+        if let Some(vfov) = self.vfov {
+            camera = camera.vfov(vfov);
+        }
+        if let Some(angle) = self.defocus_angle {
+            camera = camera.defocus_angle(angle);
+        }
+        if let Some(dist) = self.focus_dist {
+            camera = camera.focus_dist(dist);
+        }
+        camera
+    }
+}
+
+/// Parses the arguments that follow the program name.
+pub fn parse_args<I, S>(args: I) -> Result<Command, CliError>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let mut opts = Options::default();
+    let mut args = args.into_iter().map(Into::into);
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg, None),
+        };
+
+        if flag == "-h" || flag == "--help" {
+            return Ok(Command::Help);
+        }
+
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| CliError(format!("{flag} requires a value")))
+        };
+
+        match flag.as_str() {
+            "-o" | "--output" => opts.output = PathBuf::from(value()?),
+            "-s" | "--scene" => opts.scene = Some(PathBuf::from(value()?)),
+            "-w" | "--width" => opts.width = Some(parse_count(&flag, &value()?)?),
+            "-H" | "--height" => opts.height = Some(parse_count(&flag, &value()?)?),
+            "--samples" => opts.samples = Some(parse_count(&flag, &value()?)?),
+            "--max-depth" => opts.max_depth = Some(parse_number(&flag, &value()?)?),
+            "--min-depth" => opts.min_depth = Some(parse_number(&flag, &value()?)?),
+            "--seed" => opts.seed = parse_number(&flag, &value()?)?,
+            "-j" | "--threads" => opts.threads = Some(parse_count(&flag, &value()?)? as usize),
+            "--lookfrom" => opts.lookfrom = Some(parse_vec3(&flag, &value()?)?),
+            "--lookat" => opts.lookat = Some(parse_vec3(&flag, &value()?)?),
+            "--vup" => opts.vup = Some(parse_vec3(&flag, &value()?)?),
+            "--vfov" => opts.vfov = Some(parse_number(&flag, &value()?)?),
+            "--defocus-angle" => opts.defocus_angle = Some(parse_number(&flag, &value()?)?),
+            "--focus-dist" => opts.focus_dist = Some(parse_number(&flag, &value()?)?),
+            _ => return Err(CliError(format!("unknown argument '{flag}'"))),
+        }
+    }
+
+    validate(&opts)?;
+    Ok(Command::Render(Box::new(opts)))
+}
+
+fn validate(opts: &Options) -> Result<(), CliError> {
+    if ImageFormat::from_path(&opts.output).is_none() {
+        return Err(CliError(format!(
+            "unsupported output format '{}': expected .png, .ppm, .p3, .hdr, .pfm or .exr",
+            opts.output.display()
+        )));
+    }
+    Ok(())
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, CliError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| CliError(format!("invalid value '{value}' for {flag}")))
+}
+
+fn parse_count(flag: &str, value: &str) -> Result<u64, CliError> {
+    match parse_number(flag, value)? {
+        0 => Err(CliError(format!("{flag} must be at least 1"))),
+        n => Ok(n),
+    }
+}
+
+fn parse_vec3(flag: &str, value: &str) -> Result<Vec3, CliError> {
+    let parts: Vec<&str> = value.split(',').collect();
+    let [x, y, z] = parts.as_slice() else {
+        return Err(CliError(format!(
+            "invalid value '{value}' for {flag}: expected X,Y,Z"
+        )));
+    };
+    let component = |s: &str| -> Result<f64, CliError> {
+        match parse_number::<f64>(flag, s)? {
+            n if n.is_finite() => Ok(n),
+            _ => Err(CliError(format!("invalid value '{value}' for {flag}"))),
+        }
+    };
+    Ok(Vec3::new(component(x)?, component(y)?, component(z)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, CliError> {
+        match parse_args(args.iter().copied())? {
+            Command::Render(opts) => Ok(*opts),
+            Command::Help => panic!("unexpected help"),
+        }
+    }
+
+    fn error(args: &[&str]) -> String {
+        match parse(args) {
+            Ok(opts) => opts
+                .configure_camera(CameraBuilder::new())
+                .build()
+                .err()
+                .unwrap()
+                .to_string(),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_render_settings() {
+        let opts = parse(&[
+            "-o", "out.exr", "--width=640", "-H", "360", "--samples", "16", "--seed", "7",
+            "-j", "3", "--lookfrom", "-1,2.5,3", "--vfov", "35",
+        ])
+        .unwrap();
+
+        assert_eq!(opts.output, PathBuf::from("out.exr"));
+        assert_eq!((opts.width, opts.height), (Some(640), Some(360)));
+        assert_eq!(opts.samples, Some(16));
+        assert_eq!(opts.seed, 7);
+        assert_eq!(opts.threads, Some(3));
+        assert_eq!(opts.lookfrom, Some(Point3::new(-1.0, 2.5, 3.0)));
+        assert_eq!(opts.vfov, Some(35.0));
+        assert_eq!(opts.scene, None);
+    }
+
+    #[test]
+    fn overrides_camera_settings() {
+        let opts = parse(&["--height", "90", "--samples", "4", "--vfov", "45"]).unwrap();
+        let camera = opts.configure_camera(CameraBuilder::new());
+
+        assert_eq!(
+            camera,
+            CameraBuilder::new().image_height(90).samples_per_pixel(4).vfov(45.0)
+        );
+        let camera = camera.build().unwrap();
+        assert_eq!((camera.image_width(), camera.image_height()), (160, 90));
+    }
+
+    #[test]
+    fn help_wins_over_other_arguments() {
+        assert_eq!(parse_args(["--width", "10", "--help"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn rejects_invalid_combinations() {
+        assert_eq!(error(&["--width", "0"]), "--width must be at least 1");
+        assert_eq!(error(&["--width", "-5"]), "invalid value '-5' for --width");
+        assert_eq!(
+            error(&["--vfov", "-10"]),
+            "vertical field of view must be between 0 and 180 degrees, got -10"
+        );
+        assert_eq!(error(&["--samples"]), "--samples requires a value");
+        assert_eq!(
+            error(&["--lookfrom", "1,1,1", "--lookat", "1,1,1"]),
+            "lookfrom and lookat must be different points"
+        );
+        assert_eq!(
+            error(&["--vup", "-13,-2,-3"]),
+            "vup must be non-zero and not parallel to the viewing direction"
+        );
+        assert_eq!(error(&["--vup", "0,1"]), "invalid value '0,1' for --vup: expected X,Y,Z");
+        assert_eq!(
+            error(&["-o", "image.jpg"]),
+            "unsupported output format 'image.jpg': expected .png, .ppm, .p3, .hdr, .pfm or .exr"
+        );
+        assert_eq!(error(&["--frobnicate"]), "unknown argument '--frobnicate'");
+    }
+}
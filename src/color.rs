@@ -7,10 +7,15 @@ pub fn linear_to_gamma(linear_component: f64) -> f64 {
     if linear_component > 0.0 {
         return linear_component.sqrt();
     }
-    return 0.0;
+    0.0
 }
 
-pub fn write_color(pixel_color: &Color) {
+/// Perceived brightness of a linear Rec. 709 color.
+pub fn luminance(color: Color) -> f64 {
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
+
+pub fn color_to_bytes(pixel_color: &Color) -> [u8; 3] {
     let r = linear_to_gamma(pixel_color.x());
     let g = linear_to_gamma(pixel_color.y());
     let b = linear_to_gamma(pixel_color.z());
@@ -20,5 +25,5 @@ pub fn write_color(pixel_color: &Color) {
     let gbyte = (INTENSITY.clamp(g) * 256.0) as u8;
     let bbyte = (INTENSITY.clamp(b) * 256.0) as u8;
 
-    println!("{rbyte} {gbyte} {bbyte}");
+    [rbyte, gbyte, bbyte]
 }
@@ -0,0 +1,157 @@
+//! Piecewise-constant distributions for importance sampling tabulated
+//! functions, such as the brightness of an environment map.
+
+/// A density on [0, 1) proportional to a step function with one value per
+/// equal-width bucket, sampled by inverting its cumulative distribution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    /// Negative and NaN values count as zero. If nothing is left, the
+    /// distribution is uniform.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `func` is empty.
+    pub fn new(func: Vec<f64>) -> Self {
+        assert!(!func.is_empty(), "a distribution needs at least one bucket");
+        let func: Vec<f64> = func.into_iter().map(|f| if f > 0.0 { f } else { 0.0 }).collect();
+        let n = func.len() as f64;
+
+        let mut cdf = Vec::with_capacity(func.len() + 1);
+        cdf.push(0.0);
+        for f in &func {
+            cdf.push(cdf.last().unwrap() + f / n);
+        }
+        let integral = *cdf.last().unwrap();
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0.0 { *c / integral } else { i as f64 / n };
+        }
+
+        Distribution1D {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    /// The integral of the step function over [0, 1).
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Density at a point in bucket `index`.
+    pub fn pdf(&self, index: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.func[index] / self.integral
+        } else {
+            1.0
+        }
+    }
+
+    /// Maps a uniform `u` in [0, 1) to a sample `x`, returning `x`, its
+    /// density and the bucket it fell in.
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        let index = (self.cdf.partition_point(|&c| c <= u).max(1) - 1).min(self.count() - 1);
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 { (u - self.cdf[index]) / width } else { 0.0 };
+        let x = (index as f64 + offset.clamp(0.0, 1.0)) / self.count() as f64;
+        (x.min(1.0 - f64::EPSILON), self.pdf(index), index)
+    }
+
+    /// The bucket containing `x` in [0, 1].
+    fn index(&self, x: f64) -> usize {
+        ((x * self.count() as f64) as usize).min(self.count() - 1)
+    }
+}
+
+/// A density on the unit square proportional to a step function over a
+/// grid, sampled as a row from the marginal distribution and then a column
+/// within that row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// `func` holds `width * height` values row by row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the grid is empty or `func` has the wrong length.
+    pub fn new(func: &[f64], width: usize, height: usize) -> Self {
+        assert!(width > 0 && height > 0, "a distribution needs at least one bucket");
+        assert_eq!(func.len(), width * height, "expected one value per grid cell");
+        let rows: Vec<Distribution1D> =
+            func.chunks_exact(width).map(|row| Distribution1D::new(row.to_vec())).collect();
+        let marginal = Distribution1D::new(rows.iter().map(Distribution1D::integral).collect());
+        Distribution2D { rows, marginal }
+    }
+
+    /// Maps two uniform numbers to a point `(u, v)`, where `v` picks the row,
+    /// and returns it with its density.
+    pub fn sample(&self, u1: f64, u2: f64) -> (f64, f64, f64) {
+        let (v, pdf_v, row) = self.marginal.sample(u2);
+        let (u, pdf_u, _) = self.rows[row].sample(u1);
+        (u, v, pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let row = self.marginal.index(v);
+        self.marginal.pdf(row) * self.rows[row].pdf(self.rows[row].index(u))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Sampler;
+
+    #[test]
+    fn samples_follow_the_tabulated_function() {
+        let dist = Distribution1D::new(vec![1.0, 0.0, 3.0, -2.0]);
+        assert_eq!(dist.integral(), 1.0);
+        assert_eq!(dist.sample(0.0), (0.0, 1.0, 0));
+        let (x, pdf, i) = dist.sample(0.5);
+        assert!((x - 7.0 / 12.0).abs() < 1e-12);
+        assert_eq!((pdf, i), (3.0, 2));
+        let mut counts = [0; 4];
+        let mut sampler = Sampler::new(1);
+        for _ in 0..10_000 {
+            let (x, pdf, i) = dist.sample(sampler.random());
+            assert_eq!(i, dist.index(x));
+            assert_eq!(pdf, dist.pdf(i));
+            counts[i] += 1;
+        }
+        assert_eq!((counts[1], counts[3]), (0, 0));
+        assert!((counts[2] as f64 / 10_000.0 - 0.75).abs() < 0.02);
+
+        let flat = Distribution1D::new(vec![0.0, 0.0]);
+        assert_eq!(flat.sample(0.75), (0.75, 1.0, 1));
+    }
+
+    #[test]
+    fn grid_density_integrates_to_one() {
+        let func = [0.0, 1.0, 2.0, 4.0, 0.5, 0.0];
+        let dist = Distribution2D::new(&func, 3, 2);
+        let mut sampler = Sampler::new(2);
+        let mut total = 0.0;
+        for _ in 0..10_000 {
+            let (u, v, pdf) = dist.sample(sampler.random(), sampler.random());
+            assert!(pdf > 0.0);
+            assert!((dist.pdf(u, v) - pdf).abs() < 1e-12);
+            total += 1.0 / pdf;
+        }
+        // Estimates the area of the non-zero cells.
+        assert!((total / 10_000.0 - 4.0 / 6.0).abs() < 0.02);
+    }
+}
@@ -0,0 +1,174 @@
+//! Image-based lighting from equirectangular environment maps.
+
+use crate::color::{luminance, Color};
+use crate::distribution::Distribution2D;
+use crate::image::Framebuffer;
+use crate::sampler::Sampler;
+use crate::vec3::{unit_vector, Vec3};
+use std::f64::consts::PI;
+use std::io;
+use std::path::Path;
+
+/// Radiance from every direction, read from a latitude-longitude image
+/// whose top row is straight up and whose center looks down -z.
+///
+/// Directions are importance sampled in proportion to the brightness of
+/// their pixel, so a small, bright sun in the map is found by shadow rays
+/// rather than by the occasional lucky bounce.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentMap {
+    image: Framebuffer,
+    /// Counterclockwise rotation about +y, in radians.
+    rotation: f64,
+    intensity: f64,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    /// # Panics
+    ///
+    /// Panics if the image is empty.
+    pub fn new(image: Framebuffer) -> Self {
+        let (width, height) = (image.width(), image.height());
+        assert!(width > 0 && height > 0, "an environment map needs at least one pixel");
+
+        // Rows near the poles cover less of the sphere than their pixel
+        // count suggests.
+        let mut weights = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+            weights.extend((0..width).map(|x| luminance(image.get(x, y)) * sin_theta));
+        }
+        let distribution = Distribution2D::new(&weights, width, height);
+
+        EnvironmentMap {
+            image,
+            rotation: 0.0,
+            intensity: 1.0,
+            distribution,
+        }
+    }
+
+    /// Reads a map from any format [`Framebuffer::load`] supports; Radiance
+    /// `.hdr` keeps the full dynamic range.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let image = Framebuffer::load(path)?;
+        if image.width() == 0 || image.height() == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "environment map is empty"));
+        }
+        Ok(EnvironmentMap::new(image))
+    }
+
+    /// Turns the map counterclockwise about +y, seen from above.
+    pub fn with_rotation(mut self, degrees: f64) -> Self {
+        self.rotation = degrees.to_radians();
+        self
+    }
+
+    /// Scales the radiance of every pixel.
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Radiance arriving from `direction`.
+    pub fn value(&self, direction: Vec3) -> Color {
+        let (u, v) = self.to_uv(direction);
+        let (width, height) = (self.image.width(), self.image.height());
+        let x = ((u * width as f64) as usize).min(width - 1);
+        let y = ((v * height as f64) as usize).min(height - 1);
+        self.image.get(x, y) * self.intensity
+    }
+
+    /// Solid angle density of [`EnvironmentMap::random`] producing
+    /// `direction`.
+    pub fn pdf_value(&self, direction: Vec3) -> f64 {
+        let (u, v) = self.to_uv(direction);
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    /// A unit direction chosen in proportion to the brightness of the map.
+    pub fn random(&self, sampler: &mut Sampler) -> Vec3 {
+        let (u, v, _) = self.distribution.sample(sampler.random(), sampler.random());
+        let phi = 2.0 * PI * u - PI - self.rotation;
+        let theta = PI * v;
+        Vec3::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos())
+    }
+
+    /// Image coordinates in [0, 1) of `direction`, measured from the top left.
+    fn to_uv(&self, direction: Vec3) -> (f64, f64) {
+        let d = unit_vector(direction);
+        let phi = d.x().atan2(-d.z()) + self.rotation;
+        let u = ((phi + PI) / (2.0 * PI)).rem_euclid(1.0);
+        let v = d.y().clamp(-1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A dim map with one bright pixel at the given column of the middle row.
+    fn bright_spot(x: usize) -> Framebuffer {
+        let mut image = Framebuffer::new(8, 4);
+        for y in 0..4 {
+            for i in 0..8 {
+                image.set(i, y, Color::new(0.1, 0.1, 0.1));
+            }
+        }
+        image.set(x, 1, Color::new(50.0, 40.0, 30.0));
+        image
+    }
+
+    #[test]
+    fn looks_up_the_rotated_image() {
+        let map = EnvironmentMap::new(bright_spot(4)).with_intensity(2.0);
+        let ahead = Vec3::new(0.2, 0.5, -1.0);
+        assert_eq!(map.value(ahead), Color::new(100.0, 80.0, 60.0));
+        assert_eq!(map.value(Vec3::new(0.0, 1.0, 0.0)), Color::new(0.2, 0.2, 0.2));
+
+        // A quarter turn counterclockwise moves what was ahead round to the
+        // left.
+        let map = map.with_rotation(90.0);
+        assert_eq!(map.value(ahead), Color::new(0.2, 0.2, 0.2));
+        assert_eq!(map.value(Vec3::new(-1.0, 0.5, -0.2)), Color::new(100.0, 80.0, 60.0));
+    }
+
+    #[test]
+    fn importance_sampling_estimates_the_total_brightness() {
+        let image = bright_spot(2);
+        let map = EnvironmentMap::new(image.clone()).with_rotation(30.0);
+
+        // Each pixel covers a band of latitude 1/4 of pi high and a
+        // longitude range 1/8 of the full turn wide.
+        let mut expected = 0.0;
+        for y in 0..4 {
+            let band = (PI * y as f64 / 4.0).cos() - (PI * (y + 1) as f64 / 4.0).cos();
+            for x in 0..8 {
+                expected += luminance(image.get(x, y)) * band * 2.0 * PI / 8.0;
+            }
+        }
+
+        let mut sampler = Sampler::new(3);
+        let (mut estimate, mut bright) = (0.0, 0);
+        let n = 20_000;
+        for _ in 0..n {
+            let direction = map.random(&mut sampler);
+            assert!((direction.length() - 1.0).abs() < 1e-9);
+            let pdf = map.pdf_value(direction);
+            assert!(pdf > 0.0);
+            estimate += luminance(map.value(direction)) / pdf;
+            if map.value(direction).x() > 1.0 {
+                bright += 1;
+            }
+        }
+        assert!((estimate / n as f64 / expected - 1.0).abs() < 0.01);
+        // The bright pixel holds about 95% of the sampling weight.
+        assert!(bright as f64 / n as f64 > 0.9);
+    }
+}
@@ -1,17 +1,25 @@
 use crate::{
+    aabb::Aabb,
     interval::Interval,
     material::Material,
     ray::Ray,
+    sampler::Sampler,
     vec3::{dot, Point3, Vec3},
 };
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
+    /// The direction in which `u` increases across the surface. It need not
+    /// be unit length or perpendicular to `normal`, and is zero where the
+    /// surface has no such direction.
+    pub tangent: Vec3,
     pub front_face: bool,
-    pub mat: Rc<dyn Material>,
+    pub mat: Arc<dyn Material>,
 }
 
 impl HitRecord {
@@ -25,28 +33,75 @@ impl HitRecord {
     }
 }
 
-pub trait Hittable {
+pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord>;
+
+    fn bounding_box(&self) -> Aabb;
+
+    /// Like [`Hittable::hit`], but passing through participating media the
+    /// way shadow rays do; [`Hittable::transmittance`] accounts for them.
+    fn shadow_hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.hit(r, ray_t)
+    }
+
+    /// The fraction of light that makes it through the media in the object
+    /// along `r` within `ray_t`, or an unbiased estimate of it. Solid
+    /// objects let everything through here and block with `shadow_hit`.
+    fn transmittance(&self, _r: &Ray, _ray_t: Interval, _sampler: &mut Sampler) -> f64 {
+        1.0
+    }
+
+    /// Density, over solid angle, with which [`Hittable::random`] picks
+    /// `direction` from `origin` at `time`. Objects that can't be sampled as
+    /// lights leave this at zero.
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3, _time: f64) -> f64 {
+        0.0
+    }
+
+    /// A direction from `origin` toward a random point on the object where
+    /// it is at `time`, for sampling it as a light.
+    fn random(&self, _origin: Point3, _time: f64, _sampler: &mut Sampler) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    /// The emissive parts of the object, placed as they are in it, to sample
+    /// as area lights. `None` if nothing in it emits.
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        None
+    }
 }
 
-#[derive(Default)]
 pub struct HittableList {
-    objects: Vec<Rc<dyn Hittable>>,
+    objects: Vec<Arc<dyn Hittable>>,
+    bbox: Aabb,
+}
+
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HittableList {
     pub fn new() -> Self {
         HittableList {
             objects: Vec::new(),
+            bbox: Aabb::empty(),
         }
     }
 
-    pub fn add(&mut self, object: Rc<dyn Hittable>) {
+    pub fn add(&mut self, object: Arc<dyn Hittable>) {
+        self.bbox = Aabb::from_boxes(&self.bbox, &object.bounding_box());
         self.objects.push(object);
     }
 
     pub fn clear(&mut self) {
         self.objects.clear();
+        self.bbox = Aabb::empty();
+    }
+
+    pub fn objects(&self) -> &[Arc<dyn Hittable>] {
+        &self.objects
     }
 }
 
@@ -64,4 +119,54 @@ impl Hittable for HittableList {
 
         hit_record
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn shadow_hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let mut closest_so_far = ray_t.max;
+        let mut hit_record = None;
+
+        for object in &self.objects {
+            if let Some(rec) = object.shadow_hit(r, Interval::new(ray_t.min, closest_so_far)) {
+                closest_so_far = rec.t;
+                hit_record = Some(rec);
+            }
+        }
+
+        hit_record
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval, sampler: &mut Sampler) -> f64 {
+        self.objects.iter().map(|o| o.transmittance(r, ray_t, sampler)).product()
+    }
+
+    /// The average density of the objects, since `random` picks among them
+    /// uniformly.
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = self.objects.iter().map(|o| o.pdf_value(origin, direction, time)).sum();
+        total / self.objects.len() as f64
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut Sampler) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let i = (sampler.random() * self.objects.len() as f64) as usize;
+        self.objects[i.min(self.objects.len() - 1)].random(origin, time, sampler)
+    }
+
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        let mut emitters = HittableList::new();
+        for object in &self.objects {
+            if let Some(emitter) = object.emitters() {
+                emitters.add(emitter);
+            }
+        }
+        (!emitters.objects.is_empty()).then(|| Arc::new(emitters) as Arc<dyn Hittable>)
+    }
 }
@@ -0,0 +1,135 @@
+mod exr;
+mod hdr;
+mod pfm;
+mod png;
+mod ppm;
+
+use crate::color::{color_to_bytes, Color};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+pub use exr::write_exr;
+pub use hdr::{read_hdr, write_hdr};
+pub use pfm::write_pfm;
+pub use png::write_png;
+pub use ppm::{read_ppm, write_plain_ppm, write_ppm};
+
+/// A linear-radiance image filled in by the renderer.
+///
+/// Pixels are stored row by row, top to bottom, without gamma or clamping
+/// applied; encoders do that conversion when they write the image out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Color::new(0.0, 0.0, 0.0); width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [Color]> {
+        self.pixels.chunks_mut(self.width.max(1))
+    }
+
+    /// Gamma-encoded, clamped 8-bit RGB triples in row-major order.
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(color_to_bytes).collect()
+    }
+
+    /// Reads an image from `path`. Only PPM (`.ppm` or `.p3`) and Radiance
+    /// (`.hdr`) files can be decoded so far.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        match ImageFormat::from_path(path) {
+            Some(ImageFormat::Ppm | ImageFormat::PlainPpm) => {
+                read_ppm(&mut BufReader::new(File::open(path)?))
+            }
+            Some(ImageFormat::Hdr) => read_hdr(&mut BufReader::new(File::open(path)?)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported image extension for reading: {}", path.display()),
+            )),
+        }
+    }
+
+    /// Writes the image to `path`, choosing the encoder from its extension:
+    /// `.png`, `.ppm` (binary P6) or `.p3` (ASCII P3) for display, or `.hdr`,
+    /// `.pfm` or `.exr` for unclamped linear radiance.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported image extension: {}", path.display()),
+            )
+        })?;
+
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(format, &mut out)?;
+        out.flush()
+    }
+
+    pub fn write<W: Write>(&self, format: ImageFormat, out: &mut W) -> io::Result<()> {
+        match format {
+            ImageFormat::Png => write_png(self, out),
+            ImageFormat::Ppm => write_ppm(self, out),
+            ImageFormat::PlainPpm => write_plain_ppm(self, out),
+            ImageFormat::Hdr => write_hdr(self, out),
+            ImageFormat::Pfm => write_pfm(self, out),
+            ImageFormat::Exr => write_exr(self, out),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+    PlainPpm,
+    Hdr,
+    Pfm,
+    Exr,
+}
+
+impl ImageFormat {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "ppm" => Some(ImageFormat::Ppm),
+            "p3" => Some(ImageFormat::PlainPpm),
+            "hdr" => Some(ImageFormat::Hdr),
+            "pfm" => Some(ImageFormat::Pfm),
+            "exr" => Some(ImageFormat::Exr),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use super::Framebuffer;
+use std::io::{self, Write};
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+
+/// Pixel type code for 32-bit float channels.
+const FLOAT: i32 = 2;
+
+/// Writes a single-part, scanline, uncompressed OpenEXR file with 32-bit
+/// float `R`, `G` and `B` channels.
+pub fn write_exr<W: Write>(image: &Framebuffer, out: &mut W) -> io::Result<()> {
+    let width = i32::try_from(image.width()).map_err(|_| too_large())?;
+    let height = i32::try_from(image.height()).map_err(|_| too_large())?;
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&MAGIC);
+    // Version 2, single-part scanline file.
+    header.extend_from_slice(&2u32.to_le_bytes());
+
+    // Channels must be listed in alphabetical order, and pixel data follows
+    // the same order.
+    let mut chlist = Vec::new();
+    for name in ["B", "G", "R"] {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&FLOAT.to_le_bytes());
+        // pLinear and three reserved bytes.
+        chlist.extend_from_slice(&[0, 0, 0, 0]);
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    write_attribute(&mut header, "channels", "chlist", &chlist);
+
+    // NO_COMPRESSION
+    write_attribute(&mut header, "compression", "compression", &[0]);
+
+    let mut window = Vec::with_capacity(16);
+    for v in [0, 0, width - 1, height - 1] {
+        window.extend_from_slice(&v.to_le_bytes());
+    }
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+
+    // INCREASING_Y
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(&mut header, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(&mut header, "screenWindowWidth", "float", &1f32.to_le_bytes());
+    header.push(0);
+
+    out.write_all(&header)?;
+
+    // One scanline per chunk: a y coordinate and byte count, then every
+    // channel's samples for that row.
+    let row_bytes = image.width() * 3 * 4;
+    let chunk_size = 8 + row_bytes as u64;
+    let table_end = header.len() as u64 + 8 * image.height() as u64;
+    for y in 0..image.height() as u64 {
+        out.write_all(&(table_end + y * chunk_size).to_le_bytes())?;
+    }
+
+    let mut chunk = Vec::with_capacity(8 + row_bytes);
+    for y in 0..image.height() {
+        chunk.clear();
+        chunk.extend_from_slice(&(y as i32).to_le_bytes());
+        chunk.extend_from_slice(&(row_bytes as i32).to_le_bytes());
+        for channel in [2, 1, 0] {
+            for x in 0..image.width() {
+                chunk.extend_from_slice(&(image.get(x, y)[channel] as f32).to_le_bytes());
+            }
+        }
+        out.write_all(&chunk)?;
+    }
+    Ok(())
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "image dimensions exceed OpenEXR limits",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use std::collections::HashMap;
+
+    fn read_cstr(data: &[u8], pos: &mut usize) -> String {
+        let end = *pos + data[*pos..].iter().position(|&b| b == 0).unwrap();
+        let s = String::from_utf8(data[*pos..end].to_vec()).unwrap();
+        *pos = end + 1;
+        s
+    }
+
+    fn i32_at(data: &[u8], pos: usize) -> i32 {
+        i32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn round_trip_preserves_unclamped_values() {
+        let (width, height) = (4, 3);
+        let mut image = Framebuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                image.set(x, y, Color::new(x as f64 * 10.0, -(y as f64), 0.125));
+            }
+        }
+
+        let mut data = Vec::new();
+        write_exr(&image, &mut data).unwrap();
+
+        assert_eq!(&data[..4], &MAGIC);
+        assert_eq!(i32_at(&data, 4), 2);
+
+        let mut pos = 8;
+        let mut attributes = HashMap::new();
+        loop {
+            let name = read_cstr(&data, &mut pos);
+            if name.is_empty() {
+                break;
+            }
+            let kind = read_cstr(&data, &mut pos);
+            let size = i32_at(&data, pos) as usize;
+            attributes.insert(name, (kind, data[pos + 4..pos + 4 + size].to_vec()));
+            pos += 4 + size;
+        }
+
+        assert_eq!(attributes["compression"], ("compression".to_string(), vec![0]));
+        let (kind, window) = &attributes["dataWindow"];
+        assert_eq!(kind, "box2i");
+        let window: Vec<i32> = (0..4).map(|i| i32_at(window, i * 4)).collect();
+        assert_eq!(window, [0, 0, width as i32 - 1, height as i32 - 1]);
+
+        let (_, chlist) = &attributes["channels"];
+        let mut cpos = 0;
+        let mut names = Vec::new();
+        loop {
+            let name = read_cstr(chlist, &mut cpos);
+            if name.is_empty() {
+                break;
+            }
+            assert_eq!(i32_at(chlist, cpos), FLOAT);
+            cpos += 16;
+            names.push(name);
+        }
+        assert_eq!(names, ["B", "G", "R"]);
+
+        for y in 0..height {
+            let offset = u64::from_le_bytes(data[pos + y * 8..pos + y * 8 + 8].try_into().unwrap());
+            let chunk = offset as usize;
+            assert_eq!(i32_at(&data, chunk), y as i32);
+            assert_eq!(i32_at(&data, chunk + 4) as usize, width * 12);
+
+            let samples: Vec<f32> = data[chunk + 8..chunk + 8 + width * 12]
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                .collect();
+            for x in 0..width {
+                let c = image.get(x, y);
+                assert_eq!(samples[x], c.z() as f32);
+                assert_eq!(samples[width + x], c.y() as f32);
+                assert_eq!(samples[2 * width + x], c.x() as f32);
+            }
+        }
+    }
+}
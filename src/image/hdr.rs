@@ -0,0 +1,312 @@
+use super::Framebuffer;
+use crate::color::Color;
+use std::io::{self, Read, Write};
+
+/// Scanlines outside this width range can't be run-length encoded and are
+/// written flat instead.
+const RLE_WIDTHS: std::ops::RangeInclusive<usize> = 8..=0x7fff;
+
+/// Writes a Radiance RGBE (`.hdr`) image using the standard per-channel
+/// run-length encoded scanlines.
+pub fn write_hdr<W: Write>(image: &Framebuffer, out: &mut W) -> io::Result<()> {
+    write!(
+        out,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        image.height(),
+        image.width()
+    )?;
+
+    let width = image.width();
+    let mut scanline = Vec::with_capacity(width);
+    for y in 0..image.height() {
+        scanline.clear();
+        scanline.extend((0..width).map(|x| to_rgbe(image.get(x, y))));
+
+        if RLE_WIDTHS.contains(&width) {
+            out.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+            for channel in 0..4 {
+                let values: Vec<u8> = scanline.iter().map(|rgbe| rgbe[channel]).collect();
+                write_rle(&values, out)?;
+            }
+        } else {
+            for rgbe in &scanline {
+                out.write_all(rgbe)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Packs a linear color into a shared-exponent RGBE quadruple. Negative
+/// components are clamped to zero since the format can't represent them.
+fn to_rgbe(color: Color) -> [u8; 4] {
+    let r = color.x().max(0.0);
+    let g = color.y().max(0.0);
+    let b = color.z().max(0.0);
+    let v = r.max(g).max(b);
+    if v < 1e-32 || !v.is_finite() {
+        return [0, 0, 0, 0];
+    }
+
+    // v = mantissa * 2^exponent with mantissa in [0.5, 1).
+    let mut exponent = v.log2().floor() as i32 + 1;
+    let mut mantissa = v / 2f64.powi(exponent);
+    if mantissa >= 1.0 {
+        mantissa /= 2.0;
+        exponent += 1;
+    } else if mantissa < 0.5 {
+        mantissa *= 2.0;
+        exponent -= 1;
+    }
+
+    let scale = mantissa * 256.0 / v;
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (exponent + 128).clamp(0, 255) as u8,
+    ]
+}
+
+/// Encodes one channel of a scanline as a series of runs (count > 128) and
+/// literal dumps (count <= 128).
+fn write_rle<W: Write>(values: &[u8], out: &mut W) -> io::Result<()> {
+    const MIN_RUN: usize = 4;
+    let mut pos = 0;
+    while pos < values.len() {
+        // Find the next run long enough to be worth encoding.
+        let mut run_start = pos;
+        let mut run_len = 0;
+        while run_start < values.len() {
+            run_len = values[run_start..]
+                .iter()
+                .take(127)
+                .take_while(|&&v| v == values[run_start])
+                .count();
+            if run_len >= MIN_RUN {
+                break;
+            }
+            run_start += run_len;
+            run_len = 0;
+        }
+
+        for literal in values[pos..run_start].chunks(128) {
+            out.write_all(&[literal.len() as u8])?;
+            out.write_all(literal)?;
+        }
+
+        if run_len >= MIN_RUN {
+            out.write_all(&[128 + run_len as u8, values[run_start]])?;
+        }
+        pos = run_start + run_len;
+    }
+    Ok(())
+}
+
+/// Reads a Radiance RGBE (`.hdr`) image in the usual `-Y height +X width`
+/// orientation, with flat or run-length encoded scanlines.
+pub fn read_hdr<R: Read>(input: &mut R) -> io::Result<Framebuffer> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+
+    let mut pos = 0;
+    let signature = header_line(&data, &mut pos)?;
+    if signature != "#?RADIANCE" && signature != "#?RGBE" {
+        return Err(invalid("missing Radiance HDR signature"));
+    }
+    loop {
+        let line = header_line(&data, &mut pos)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=")
+            && format != "32-bit_rle_rgbe"
+        {
+            return Err(invalid(format!("unsupported HDR format {format:?}")));
+        }
+    }
+
+    let resolution = header_line(&data, &mut pos)?;
+    let fields: Vec<&str> = resolution.split_whitespace().collect();
+    let (height, width) = match fields[..] {
+        ["-Y", height, "+X", width] => (dimension(height)?, dimension(width)?),
+        _ => return Err(invalid(format!("unsupported HDR resolution {resolution:?}"))),
+    };
+
+    let mut image = Framebuffer::new(width, height);
+    let mut scanline = vec![[0; 4]; width];
+    for y in 0..height {
+        read_scanline(&data, &mut pos, &mut scanline)?;
+        for (x, rgbe) in scanline.iter().enumerate() {
+            image.set(x, y, from_rgbe(rgbe));
+        }
+    }
+    Ok(image)
+}
+
+fn read_scanline(data: &[u8], pos: &mut usize, scanline: &mut [[u8; 4]]) -> io::Result<()> {
+    let width = scanline.len();
+    let truncated = || invalid("HDR raster is truncated");
+    let head = data.get(*pos..*pos + 4).ok_or_else(truncated)?;
+
+    // Like Radiance itself, treat anything that doesn't start with 2, 2 and
+    // a plausible width as flat pixels.
+    if !(RLE_WIDTHS.contains(&width) && head[0] == 2 && head[1] == 2 && head[2] & 0x80 == 0) {
+        let raster = data.get(*pos..*pos + width * 4).ok_or_else(truncated)?;
+        for (rgbe, bytes) in scanline.iter_mut().zip(raster.chunks_exact(4)) {
+            rgbe.copy_from_slice(bytes);
+        }
+        *pos += width * 4;
+        return Ok(());
+    }
+
+    if ((head[2] as usize) << 8 | head[3] as usize) != width {
+        return Err(invalid("HDR scanline width does not match the image"));
+    }
+    *pos += 4;
+    let overflow = || invalid("HDR run does not fit its scanline");
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = *data.get(*pos).ok_or_else(truncated)? as usize;
+            if count > 128 {
+                let len = count - 128;
+                let value = *data.get(*pos + 1).ok_or_else(truncated)?;
+                let run = scanline.get_mut(x..x + len).ok_or_else(overflow)?;
+                for rgbe in run {
+                    rgbe[channel] = value;
+                }
+                *pos += 2;
+                x += len;
+            } else {
+                let literal = data.get(*pos + 1..*pos + 1 + count).ok_or_else(truncated)?;
+                let run = scanline.get_mut(x..x + count).ok_or_else(overflow)?;
+                if count == 0 {
+                    return Err(overflow());
+                }
+                for (rgbe, &value) in run.iter_mut().zip(literal) {
+                    rgbe[channel] = value;
+                }
+                *pos += 1 + count;
+                x += count;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Unpacks a shared-exponent quadruple, taking each mantissa from the middle
+/// of the range it was rounded down from.
+fn from_rgbe(rgbe: &[u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let f = 2f64.powi(rgbe[3] as i32 - (128 + 8));
+    Color::new(
+        (rgbe[0] as f64 + 0.5) * f,
+        (rgbe[1] as f64 + 0.5) * f,
+        (rgbe[2] as f64 + 0.5) * f,
+    )
+}
+
+fn header_line(data: &[u8], pos: &mut usize) -> io::Result<String> {
+    let rest = data.get(*pos..).unwrap_or(&[]);
+    let len = rest
+        .iter()
+        .position(|&b| b == b'\n')
+        .ok_or_else(|| invalid("HDR header is truncated"))?;
+    *pos += len + 1;
+    Ok(String::from_utf8_lossy(&rest[..len]).trim_end().to_string())
+}
+
+fn dimension(token: &str) -> io::Result<usize> {
+    token
+        .parse()
+        .map_err(|_| invalid(format!("invalid HDR dimension {token:?}")))
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks the header and, where the width allows it, that scanlines are
+    /// run-length encoded, then decodes the image.
+    fn decode(data: &[u8]) -> Framebuffer {
+        let header_end = data.windows(2).position(|w| w == b"\n\n").unwrap() + 2;
+        let header = std::str::from_utf8(&data[..header_end]).unwrap();
+        assert!(header.starts_with("#?RADIANCE\n"));
+        assert!(header.contains("FORMAT=32-bit_rle_rgbe\n"));
+
+        let res_end = header_end + data[header_end..].iter().position(|&b| b == b'\n').unwrap();
+        let image = read_hdr(&mut &data[..]).unwrap();
+        let width = image.width();
+        if RLE_WIDTHS.contains(&width) {
+            let scanline = &data[res_end + 1..res_end + 5];
+            assert_eq!(scanline, &[2, 2, (width >> 8) as u8, (width & 0xff) as u8]);
+        } else {
+            assert_eq!(data.len() - res_end - 1, width * image.height() * 4);
+        }
+        image
+    }
+
+    fn round_trip(width: usize, height: usize) {
+        let mut image = Framebuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                // Long constant stretches exercise runs, the ramp exercises
+                // literals, and the large values check nothing is clamped.
+                let c = if x < width / 3 {
+                    Color::new(0.5, 0.5, 0.5)
+                } else {
+                    Color::new(x as f64 * 3.7, y as f64 * 0.01, 1000.0 / (x + 1) as f64)
+                };
+                image.set(x, y, c);
+            }
+        }
+
+        let mut data = Vec::new();
+        write_hdr(&image, &mut data).unwrap();
+        let decoded = decode(&data);
+        assert_eq!((decoded.width(), decoded.height()), (width, height));
+
+        for (expected, actual) in image.pixels().iter().zip(decoded.pixels()) {
+            let max = expected.x().max(expected.y()).max(expected.z());
+            for i in 0..3 {
+                assert!((expected[i] - actual[i]).abs() <= max / 128.0);
+            }
+        }
+    }
+
+    #[test]
+    fn run_length_encoded_round_trip() {
+        round_trip(300, 4);
+    }
+
+    #[test]
+    fn flat_scanline_round_trip() {
+        round_trip(5, 3);
+    }
+
+    #[test]
+    fn read_rejects_unsupported_or_damaged_files() {
+        let read = |data: &[u8]| read_hdr(&mut &data[..]).unwrap_err().to_string();
+        assert_eq!(read(b"P6\n1 1\n255\n"), "missing Radiance HDR signature");
+        assert_eq!(
+            read(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\0\0\0\0"),
+            "unsupported HDR format \"32-bit_rle_xyze\""
+        );
+        assert_eq!(
+            read(b"#?RADIANCE\n\n+X 1 -Y 1\n\0\0\0\0"),
+            "unsupported HDR resolution \"+X 1 -Y 1\""
+        );
+        assert_eq!(read(b"#?RADIANCE\n\n-Y 2 +X 1\n\0\0\0\0"), "HDR raster is truncated");
+        assert_eq!(
+            read(b"#?RADIANCE\n\n-Y 1 +X 8\n\x02\x02\x00\x08\x89\x01"),
+            "HDR run does not fit its scanline"
+        );
+    }
+}
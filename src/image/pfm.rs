@@ -0,0 +1,58 @@
+use super::Framebuffer;
+use std::io::{self, Write};
+
+/// Writes a color Portable Float Map: 32-bit little-endian floats, rows
+/// stored bottom to top as the format requires.
+pub fn write_pfm<W: Write>(image: &Framebuffer, out: &mut W) -> io::Result<()> {
+    // A negative scale marks the raster as little-endian.
+    write!(out, "PF\n{} {}\n-1.0\n", image.width(), image.height())?;
+
+    let mut row = Vec::with_capacity(image.width() * 12);
+    for y in (0..image.height()).rev() {
+        row.clear();
+        for x in 0..image.width() {
+            let c = image.get(x, y);
+            for component in [c.x(), c.y(), c.z()] {
+                row.extend_from_slice(&(component as f32).to_le_bytes());
+            }
+        }
+        out.write_all(&row)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn round_trip_preserves_unclamped_values() {
+        let mut image = Framebuffer::new(3, 2);
+        image.set(0, 0, Color::new(12.5, 0.0, -0.25));
+        image.set(2, 1, Color::new(0.1, 4096.0, 1e-6));
+
+        let mut data = Vec::new();
+        write_pfm(&image, &mut data).unwrap();
+
+        let header = b"PF\n3 2\n-1.0\n";
+        assert_eq!(&data[..header.len()], header);
+        let floats: Vec<f32> = data[header.len()..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(floats.len(), 3 * 2 * 3);
+
+        for y in 0..2 {
+            // The file's first row is the image's bottom row.
+            let file_row = 1 - y;
+            for x in 0..3 {
+                let expected = image.get(x, y);
+                let i = (file_row * 3 + x) * 3;
+                assert_eq!(floats[i], expected.x() as f32);
+                assert_eq!(floats[i + 1], expected.y() as f32);
+                assert_eq!(floats[i + 2], expected.z() as f32);
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use super::Framebuffer;
+use std::io::{self, Write};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Largest payload a single stored deflate block can carry.
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+/// Writes an 8-bit truecolor PNG.
+///
+/// Scanlines use filter type 0 and the zlib stream is made of stored
+/// (uncompressed) deflate blocks, which keeps the encoder small while still
+/// producing a file that any decoder accepts.
+pub fn write_png<W: Write>(image: &Framebuffer, out: &mut W) -> io::Result<()> {
+    let width = u32::try_from(image.width()).map_err(|_| too_large())?;
+    let height = u32::try_from(image.height()).map_err(|_| too_large())?;
+
+    out.write_all(&SIGNATURE)?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // Bit depth 8, color type 2 (RGB), default compression, filter and
+    // interlace methods.
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_chunk(out, b"IHDR", &ihdr)?;
+
+    let rgb = image.to_rgb8();
+    let stride = image.width() * 3;
+    let mut raw = Vec::with_capacity((stride + 1) * image.height());
+    for row in rgb.chunks_exact(stride.max(1)) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    write_chunk(out, b"IDAT", &zlib_stored(&raw))?;
+
+    write_chunk(out, b"IEND", &[])
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "image dimensions exceed PNG limits",
+    )
+}
+
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(data.len()).map_err(|_| too_large())?;
+    out.write_all(&len.to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+
+    let mut crc = Crc32::new();
+    crc.update(kind);
+    crc.update(data);
+    out.write_all(&crc.finish().to_be_bytes())
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut out = Vec::with_capacity(data.len() + blocks * 5 + 6);
+
+    // CMF/FLG: deflate with a 32K window, no preset dictionary, fastest level.
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0x00, 0x00, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let is_final = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        out.push(is_final as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the largest run that can't overflow `b` before reducing.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+struct Crc32 {
+    table: [u32; 256],
+    value: u32,
+}
+
+impl Crc32 {
+    fn new() -> Self {
+        let mut table = [0u32; 256];
+        for (n, entry) in table.iter_mut().enumerate() {
+            let mut c = n as u32;
+            for _ in 0..8 {
+                c = if c & 1 != 0 {
+                    0xedb8_8320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+            }
+            *entry = c;
+        }
+        Crc32 {
+            table,
+            value: 0xffff_ffff,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.value = self.table[((self.value ^ byte as u32) & 0xff) as usize] ^ (self.value >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        self.value ^ 0xffff_ffff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    /// Walks the chunk list, checking every CRC, and returns the IHDR fields
+    /// and the inflated IDAT payload.
+    fn decode(data: &[u8]) -> (u32, u32, Vec<u8>) {
+        assert_eq!(&data[..8], &SIGNATURE);
+        let mut pos = 8;
+        let mut header = None;
+        let mut idat = Vec::new();
+        loop {
+            let len = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            let kind = &data[pos + 4..pos + 8];
+            let body = &data[pos + 8..pos + 8 + len];
+            let stored_crc = u32::from_be_bytes(data[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+
+            let mut crc = Crc32::new();
+            crc.update(kind);
+            crc.update(body);
+            assert_eq!(crc.finish(), stored_crc);
+
+            match kind {
+                b"IHDR" => {
+                    let width = u32::from_be_bytes(body[0..4].try_into().unwrap());
+                    let height = u32::from_be_bytes(body[4..8].try_into().unwrap());
+                    assert_eq!(&body[8..], &[8, 2, 0, 0, 0]);
+                    header = Some((width, height));
+                }
+                b"IDAT" => idat.extend_from_slice(body),
+                b"IEND" => break,
+                _ => panic!("unexpected chunk"),
+            }
+            pos += 12 + len;
+        }
+
+        let (width, height) = header.unwrap();
+        (width, height, inflate_stored(&idat))
+    }
+
+    fn inflate_stored(zlib: &[u8]) -> Vec<u8> {
+        assert_eq!((zlib[0] as u16 * 256 + zlib[1] as u16) % 31, 0);
+        let mut pos = 2;
+        let mut out = Vec::new();
+        loop {
+            let header = zlib[pos];
+            assert_eq!(header >> 1, 0, "only stored blocks are expected");
+            let len = u16::from_le_bytes([zlib[pos + 1], zlib[pos + 2]]);
+            let nlen = u16::from_le_bytes([zlib[pos + 3], zlib[pos + 4]]);
+            assert_eq!(len, !nlen);
+            pos += 5;
+            out.extend_from_slice(&zlib[pos..pos + len as usize]);
+            pos += len as usize;
+            if header & 1 == 1 {
+                break;
+            }
+        }
+        let checksum = u32::from_be_bytes(zlib[pos..pos + 4].try_into().unwrap());
+        assert_eq!(checksum, adler32(&out));
+        out
+    }
+
+    fn round_trip(width: usize, height: usize) {
+        let mut image = Framebuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let c = Color::new((x % 17) as f64 / 16.0, (y % 13) as f64 / 12.0, 0.5);
+                image.set(x, y, c);
+            }
+        }
+
+        let mut data = Vec::new();
+        write_png(&image, &mut data).unwrap();
+        let (w, h, raw) = decode(&data);
+        assert_eq!((w as usize, h as usize), (width, height));
+
+        let stride = width * 3;
+        let mut rgb = Vec::new();
+        for row in raw.chunks_exact(stride + 1) {
+            assert_eq!(row[0], 0);
+            rgb.extend_from_slice(&row[1..]);
+        }
+        assert_eq!(rgb, image.to_rgb8());
+    }
+
+    #[test]
+    fn small_image_round_trip() {
+        round_trip(5, 3);
+    }
+
+    #[test]
+    fn image_spanning_several_deflate_blocks_round_trips() {
+        round_trip(200, 150);
+    }
+
+    #[test]
+    fn known_checksums() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+}
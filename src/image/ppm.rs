@@ -0,0 +1,195 @@
+use super::Framebuffer;
+use crate::color::Color;
+use std::io::{self, Read, Write};
+
+/// Binary (P6) PPM: an ASCII header followed by raw 8-bit RGB triples.
+pub fn write_ppm<W: Write>(image: &Framebuffer, out: &mut W) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", image.width(), image.height())?;
+    out.write_all(&image.to_rgb8())
+}
+
+/// Plain (P3) PPM: every sample written as decimal text, one pixel per line.
+pub fn write_plain_ppm<W: Write>(image: &Framebuffer, out: &mut W) -> io::Result<()> {
+    write!(out, "P3\n{} {}\n255\n", image.width(), image.height())?;
+    for rgb in image.to_rgb8().chunks_exact(3) {
+        writeln!(out, "{} {} {}", rgb[0], rgb[1], rgb[2])?;
+    }
+    Ok(())
+}
+
+/// Reads a binary (P6) or plain (P3) PPM, undoing the gamma encoding so
+/// the pixels come back as linear colors.
+pub fn read_ppm<R: Read>(input: &mut R) -> io::Result<Framebuffer> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+
+    let mut pos = 0;
+    let magic = header_token(&data, &mut pos)?;
+    let width = header_number(&data, &mut pos)?;
+    let height = header_number(&data, &mut pos)?;
+    let maxval = header_number(&data, &mut pos)?;
+    if maxval == 0 || maxval > 65535 {
+        return Err(invalid(format!("invalid PPM maximum value {maxval}")));
+    }
+    let count = width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(3))
+        .ok_or_else(|| invalid("PPM dimensions are too large"))?;
+
+    let samples: Vec<usize> = match magic.as_str() {
+        "P6" => {
+            // Exactly one whitespace byte separates the header from the raster.
+            let raster = data.get(pos + 1..).unwrap_or(&[]);
+            let bytes_per_sample = if maxval < 256 { 1 } else { 2 };
+            if raster.len() < count * bytes_per_sample {
+                return Err(invalid("PPM raster is truncated"));
+            }
+            raster[..count * bytes_per_sample]
+                .chunks_exact(bytes_per_sample)
+                .map(|b| b.iter().fold(0, |n, &byte| (n << 8) | byte as usize))
+                .collect()
+        }
+        "P3" => {
+            let samples = (0..count)
+                .map(|_| header_number(&data, &mut pos))
+                .collect::<io::Result<Vec<_>>>()?;
+            if samples.iter().any(|&s| s > maxval) {
+                return Err(invalid("PPM sample exceeds the maximum value"));
+            }
+            samples
+        }
+        other => return Err(invalid(format!("unsupported PPM magic number {other:?}"))),
+    };
+
+    let mut image = Framebuffer::new(width, height);
+    let linear = |s: usize| {
+        let gamma = s as f64 / maxval as f64;
+        gamma * gamma
+    };
+    for (i, rgb) in samples.chunks_exact(3).enumerate() {
+        let color = Color::new(linear(rgb[0]), linear(rgb[1]), linear(rgb[2]));
+        image.set(i % width, i / width, color);
+    }
+    Ok(image)
+}
+
+/// The next whitespace-separated header token, skipping `#` comments.
+fn header_token(data: &[u8], pos: &mut usize) -> io::Result<String> {
+    loop {
+        match data.get(*pos) {
+            Some(b) if b.is_ascii_whitespace() => *pos += 1,
+            Some(b'#') => {
+                while data.get(*pos).is_some_and(|&b| b != b'\n') {
+                    *pos += 1;
+                }
+            }
+            Some(_) => break,
+            None => return Err(invalid("PPM header is truncated")),
+        }
+    }
+    let start = *pos;
+    while data.get(*pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+        *pos += 1;
+    }
+    Ok(String::from_utf8_lossy(&data[start..*pos]).into_owned())
+}
+
+fn header_number(data: &[u8], pos: &mut usize) -> io::Result<usize> {
+    let token = header_token(data, pos)?;
+    token
+        .parse()
+        .map_err(|_| invalid(format!("invalid number {token:?} in PPM")))
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: usize, height: usize) -> Framebuffer {
+        let mut image = Framebuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let c = Color::new(x as f64 / width as f64, y as f64 / height as f64, 0.25);
+                image.set(x, y, c);
+            }
+        }
+        image
+    }
+
+    /// Splits a PPM into its header fields and the byte offset of the raster.
+    fn parse_header(data: &[u8]) -> (String, usize, usize, usize, usize) {
+        let mut fields = Vec::new();
+        let mut pos = 0;
+        while fields.len() < 4 {
+            while data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            let start = pos;
+            while !data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            fields.push(String::from_utf8(data[start..pos].to_vec()).unwrap());
+        }
+        (
+            fields[0].clone(),
+            fields[1].parse().unwrap(),
+            fields[2].parse().unwrap(),
+            fields[3].parse().unwrap(),
+            pos + 1,
+        )
+    }
+
+    #[test]
+    fn binary_ppm_round_trip() {
+        let image = gradient(7, 5);
+        let mut data = Vec::new();
+        write_ppm(&image, &mut data).unwrap();
+
+        let (magic, width, height, maxval, offset) = parse_header(&data);
+        assert_eq!(magic, "P6");
+        assert_eq!((width, height, maxval), (7, 5, 255));
+        assert_eq!(&data[offset..], image.to_rgb8().as_slice());
+    }
+
+    #[test]
+    fn plain_ppm_round_trip() {
+        let image = gradient(4, 3);
+        let mut data = Vec::new();
+        write_plain_ppm(&image, &mut data).unwrap();
+
+        let (magic, width, height, maxval, offset) = parse_header(&data);
+        assert_eq!(magic, "P3");
+        assert_eq!((width, height, maxval), (4, 3, 255));
+
+        let samples: Vec<u8> = std::str::from_utf8(&data[offset..])
+            .unwrap()
+            .split_ascii_whitespace()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(samples, image.to_rgb8());
+    }
+
+    #[test]
+    fn read_undoes_gamma_encoding() {
+        let image = gradient(6, 4);
+        for write in [write_ppm, write_plain_ppm] {
+            let mut data = Vec::new();
+            write(&image, &mut data).unwrap();
+            let decoded = read_ppm(&mut data.as_slice()).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (6, 4));
+            assert_eq!(decoded.to_rgb8(), image.to_rgb8());
+        }
+    }
+
+    #[test]
+    fn read_skips_header_comments() {
+        let data = b"P3\n# a comment\n1 1 # width and height\n255\n255 0 255\n";
+        let image = read_ppm(&mut data.as_slice()).unwrap();
+        assert_eq!(image.get(0, 0), Color::new(1.0, 0.0, 1.0));
+        assert!(read_ppm(&mut b"P6\n2 2\n255\n\0\0".as_slice()).is_err());
+    }
+}
@@ -9,6 +9,13 @@ impl Interval {
         Interval { min, max }
     }
 
+    pub const fn enclosing(a: Interval, b: Interval) -> Self {
+        Interval {
+            min: if a.min <= b.min { a.min } else { b.min },
+            max: if a.max >= b.max { a.max } else { b.max },
+        }
+    }
+
     pub const fn size(&self) -> f64 {
         self.max - self.min
     }
@@ -30,6 +37,14 @@ impl Interval {
         x
     }
 
+    pub const fn expand(&self, delta: f64) -> Self {
+        let padding = delta / 2.0;
+        Interval {
+            min: self.min - padding,
+            max: self.max + padding,
+        }
+    }
+
     pub const fn empty() -> Self {
         Interval {
             min: f64::INFINITY,
@@ -0,0 +1,85 @@
+//! A Monte Carlo path tracer.
+//!
+//! Build a world out of [`Hittable`] objects and [`Material`]s (or load one
+//! with [`scene::load_scene`]), configure a [`Camera`] with a
+//! [`CameraBuilder`], and call [`Camera::render`] to get a [`Framebuffer`]
+//! that can be saved in any of the supported [`ImageFormat`]s.
+//!
+//! ```no_run
+//! use raytracer::{
+//!     BvhNode, CameraBuilder, Color, HittableList, Lambertian, Point3, Sphere,
+//! };
+//! use std::sync::Arc;
+//!
+//! let mut world = HittableList::new();
+//! let matte = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+//! world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, matte)));
+//!
+//! let camera = CameraBuilder::new().image_width(400).build().unwrap();
+//! let image = camera.render(&BvhNode::from_list(&world));
+//! image.save("sphere.png").unwrap();
+//! ```
+
+pub mod aabb;
+pub mod background;
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod distribution;
+pub mod environment;
+pub mod hit;
+pub mod image;
+pub mod interval;
+pub mod light;
+pub mod material;
+pub mod matrix;
+pub mod medium;
+pub mod microfacet;
+pub mod obj;
+pub mod onb;
+pub mod pdf;
+pub mod perlin;
+pub mod phase;
+pub mod quad;
+pub mod ray;
+pub mod sampler;
+pub mod scene;
+pub mod sky;
+pub mod sphere;
+pub mod texture;
+pub mod transform;
+pub mod triangle;
+pub mod vec3;
+
+pub use aabb::Aabb;
+pub use background::Background;
+pub use bvh::BvhNode;
+pub use camera::{Camera, CameraBuilder, CameraError};
+pub use color::Color;
+pub use distribution::{Distribution1D, Distribution2D};
+pub use environment::EnvironmentMap;
+pub use hit::{HitRecord, Hittable, HittableList};
+pub use image::{Framebuffer, ImageFormat};
+pub use interval::Interval;
+pub use light::{DirectionalLight, Light, LightList, LightSample, PointLight, SpotLight};
+pub use material::{
+    Anisotropic, ComplexIor, Conductor, Dielectric, DiffuseLight, Isotropic, Lambertian, Material,
+    Metal, ScatterResult, VolumeScatter,
+};
+pub use matrix::Mat4;
+pub use medium::{ConstantMedium, DensityField, HeterogeneousMedium, NoiseDensity, VoxelGrid};
+pub use microfacet::TrowbridgeReitz;
+pub use obj::{load_obj, Mesh, ObjError};
+pub use onb::Onb;
+pub use pdf::{CosinePdf, MicrofacetPdf, MixturePdf, Pdf, PhasePdf, SpherePdf};
+pub use phase::{HenyeyGreenstein, IsotropicPhase, PhaseFunction};
+pub use quad::{make_box, Quad};
+pub use ray::Ray;
+pub use sampler::Sampler;
+pub use scene::{Scene, SceneError};
+pub use sky::Daylight;
+pub use sphere::Sphere;
+pub use texture::{CheckerTexture, ImageTexture, NoiseStyle, NoiseTexture, SolidColor, Texture};
+pub use transform::{RotateX, RotateY, RotateZ, Scale, Transform, Translate};
+pub use triangle::Triangle;
+pub use vec3::{Point3, Vec3};
@@ -0,0 +1,226 @@
+//! Lights the renderer samples directly at every diffuse bounce.
+//!
+//! Emissive surfaces, as [`Hittable::emitters`] finds them, are area
+//! lights, sampled through [`Hittable::pdf_value`] and
+//! [`Hittable::random`]. Punctual lights have no surface at all: they are
+//! infinitely small or infinitely far away, so no path can hit them by
+//! chance and shadow rays are the only way their light reaches the scene.
+
+use crate::{
+    color::Color,
+    hit::{Hittable, HittableList},
+    vec3::{dot, unit_vector, Point3, Vec3},
+};
+use std::sync::Arc;
+
+/// Light arriving at a point from a punctual light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSample {
+    /// Unit vector from the point toward the light.
+    pub direction: Vec3,
+    /// How far the light is along `direction`; infinite for distant lights.
+    pub distance: f64,
+    /// Irradiance, in W/m², on a surface facing the light.
+    pub irradiance: Color,
+}
+
+/// A light with a single direction toward it from any point.
+pub trait Light: Send + Sync {
+    /// The light arriving at `p`, or `None` if none can.
+    fn sample(&self, p: Point3) -> Option<LightSample>;
+}
+
+/// Shines equally in every direction from a point, with radiant
+/// `intensity` in W/sr falling off with the square of the distance.
+#[derive(Debug, Clone, Copy)]
+pub struct PointLight {
+    position: Point3,
+    intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, p: Point3) -> Option<LightSample> {
+        inverse_square(self.position, self.intensity, p)
+    }
+}
+
+/// A point light restricted to a cone around `direction`. The intensity is
+/// full within `falloff_start` degrees of the axis and fades smoothly to
+/// zero at `cone_angle` degrees.
+#[derive(Debug, Clone, Copy)]
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3,
+    intensity: Color,
+    cos_cone: f64,
+    cos_falloff_start: f64,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point3,
+        direction: Vec3,
+        intensity: Color,
+        cone_angle: f64,
+        falloff_start: f64,
+    ) -> Self {
+        let cone_angle = cone_angle.clamp(0.0, 180.0);
+        SpotLight {
+            position,
+            direction: unit_vector(direction),
+            intensity,
+            cos_cone: cone_angle.to_radians().cos(),
+            cos_falloff_start: falloff_start.clamp(0.0, cone_angle).to_radians().cos(),
+        }
+    }
+
+    /// The fraction of the full intensity sent along unit vector `w`.
+    fn falloff(&self, w: Vec3) -> f64 {
+        let cos_theta = dot(w, self.direction);
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        if cos_theta <= self.cos_cone {
+            return 0.0;
+        }
+        let x = (cos_theta - self.cos_cone) / (self.cos_falloff_start - self.cos_cone);
+        x * x * (3.0 - 2.0 * x)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, p: Point3) -> Option<LightSample> {
+        let mut sample = inverse_square(self.position, self.intensity, p)?;
+        let falloff = self.falloff(-sample.direction);
+        if falloff <= 0.0 {
+            return None;
+        }
+        sample.irradiance *= falloff;
+        Some(sample)
+    }
+}
+
+/// Parallel light from infinitely far away, such as the sun, travelling in
+/// `direction` and giving `irradiance` in W/m² to surfaces facing it.
+#[derive(Debug, Clone, Copy)]
+pub struct DirectionalLight {
+    direction: Vec3,
+    irradiance: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, irradiance: Color) -> Self {
+        DirectionalLight {
+            direction: unit_vector(direction),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _p: Point3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            distance: f64::INFINITY,
+            irradiance: self.irradiance,
+        })
+    }
+}
+
+fn inverse_square(position: Point3, intensity: Color, p: Point3) -> Option<LightSample> {
+    let to_light = position - p;
+    let distance_squared = to_light.length_squared();
+    if distance_squared <= 0.0 {
+        return None;
+    }
+    let distance = distance_squared.sqrt();
+    Some(LightSample {
+        direction: to_light / distance,
+        distance,
+        irradiance: intensity / distance_squared,
+    })
+}
+
+/// Every light in a scene: emissive objects, which must also be part of the
+/// world, and punctual lights.
+#[derive(Default)]
+pub struct LightList {
+    area: HittableList,
+    punctual: Vec<Arc<dyn Light>>,
+}
+
+impl LightList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds emissive geometry to sample directly.
+    pub fn add_area(&mut self, object: Arc<dyn Hittable>) {
+        self.area.add(object);
+    }
+
+    pub fn add(&mut self, light: Arc<dyn Light>) {
+        self.punctual.push(light);
+    }
+
+    pub fn area(&self) -> &HittableList {
+        &self.area
+    }
+
+    pub fn punctual(&self) -> &[Arc<dyn Light>] {
+        &self.punctual
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.area.objects().is_empty() && self.punctual.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_and_spot_lights_fall_off_with_distance_and_angle() {
+        let intensity = Color::new(8.0, 8.0, 8.0);
+        let point = PointLight::new(Point3::new(0.0, 2.0, 0.0), intensity);
+        let sample = point.sample(Point3::new(0.0, 0.0, 0.0)).unwrap();
+        assert_eq!(sample.direction, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(sample.distance, 2.0);
+        assert_eq!(sample.irradiance, Color::new(2.0, 2.0, 2.0));
+
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let spot = SpotLight::new(Point3::new(0.0, 1.0, 0.0), down, intensity, 45.0, 30.0);
+        let at = |x: f64| spot.sample(Point3::new(x, 0.0, 0.0)).map(|s| s.irradiance.x());
+        assert_eq!(at(0.0), Some(8.0));
+        // Inside the full-intensity cone, only the distance matters.
+        assert_eq!(at(0.5), Some(8.0 / 1.25));
+        let fading = at(0.9).unwrap();
+        assert!(fading > 0.0 && fading < 8.0 / 1.81);
+        assert_eq!(at(1.5), None);
+
+        let hard = SpotLight::new(Point3::new(0.0, 1.0, 0.0), down, intensity, 45.0, 90.0);
+        let edge = hard.sample(Point3::new(0.9, 0.0, 0.0)).unwrap().irradiance.x();
+        assert!((edge - 8.0 / 1.81).abs() < 1e-12);
+    }
+
+    #[test]
+    fn directional_light_is_the_same_everywhere() {
+        let sun = DirectionalLight::new(Vec3::new(0.0, -2.0, 0.0), Color::new(3.0, 3.0, 3.0));
+        for p in [Point3::new(0.0, 0.0, 0.0), Point3::new(100.0, -5.0, 7.0)] {
+            let sample = sun.sample(p).unwrap();
+            assert_eq!(sample.direction, Vec3::new(0.0, 1.0, 0.0));
+            assert_eq!(sample.distance, f64::INFINITY);
+            assert_eq!(sample.irradiance, Color::new(3.0, 3.0, 3.0));
+        }
+    }
+}
@@ -1,28 +1,62 @@
-mod camera;
-mod color;
-mod hit;
-mod interval;
-mod material;
-mod ray;
-mod sphere;
-mod vec3;
-
-use crate::camera::Camera;
-use crate::color::Color;
-use crate::hit::HittableList;
-use crate::material::{Dielectric, Lambertian, Metal};
-use crate::sphere::Sphere;
-use crate::vec3::Point3;
-use rand::Rng;
-use std::rc::Rc;
+mod cli;
+
+use crate::cli::{parse_args, Command, USAGE};
+use raytracer::scene::load_scene;
+use raytracer::{
+    BvhNode, CameraBuilder, Color, Dielectric, HittableList, Lambertian, LightList, Material, Metal,
+    Point3, Sampler, Sphere,
+};
+use std::process;
+use std::sync::Arc;
 
 fn main() {
+    let opts = match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Render(opts)) => opts,
+        Ok(Command::Help) => {
+            print!("{USAGE}");
+            return;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    let (world, lights, camera) = match &opts.scene {
+        Some(path) => match load_scene(path) {
+            Ok(scene) => (scene.world, scene.lights, scene.camera),
+            Err(err) => {
+                eprintln!("error: {}: {err}", path.display());
+                process::exit(1);
+            }
+        },
+        None => (random_spheres(opts.seed), LightList::new(), CameraBuilder::new()),
+    };
+
+    let cam = match opts.configure_camera(camera).build() {
+        Ok(cam) => cam,
+        Err(err) => {
+            eprintln!("error: invalid camera settings: {err}");
+            process::exit(2);
+        }
+    };
+
+    let world = BvhNode::from_list(&world);
+
+    let image = cam.render_with_lights(&world, &lights);
+    if let Err(err) = image.save(&opts.output) {
+        eprintln!("error: could not write {}: {err}", opts.output.display());
+        process::exit(1);
+    }
+}
+
+fn random_spheres(seed: u64) -> HittableList {
     let mut world = HittableList::new();
-    let mut rng = rand::rng();
+    let mut rng = Sampler::new(seed);
 
-    let ground_mat = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
 
-    world.add(Rc::new(Sphere::new(
+    world.add(Arc::new(Sphere::new(
         Point3::new(0.0, -1000.0, 0.0),
         1000.0,
         ground_mat,
@@ -30,44 +64,42 @@ fn main() {
 
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat = rng.random_range(0.0..1.0);
-            let center = Point3::new(a as f64 + 0.9 * rng.random_range(0.0..1.0), 0.2, b as f64 + 0.9 * rng.random_range(0.0..1.0));
+            let choose_mat = rng.random();
+            let center = Point3::new(a as f64 + 0.9 * rng.random(), 0.2, b as f64 + 0.9 * rng.random());
 
-            let sphere_mat: Rc<dyn crate::material::Material> = if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+            let sphere_mat: Arc<dyn Material> = if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                 if choose_mat < 0.8 {
-                    let albedo = Color::random_from_range(0.0, 1.0) * Color::random_from_range(0.0, 1.0);
-                    Rc::new(Lambertian::new(albedo))
+                    let albedo = Color::random(&mut rng) * Color::random(&mut rng);
+                    Arc::new(Lambertian::new(albedo))
                 } else if choose_mat < 0.95 {
-                    let albedo = Color::random_from_range(0.5, 1.0);
-                    let fuzz = rng.random_range(0.0..0.5);
-                    Rc::new(Metal::new(albedo, fuzz))
+                    let albedo = Color::random_from_range(&mut rng, 0.5, 1.0);
+                    let fuzz = rng.random_range(0.0, 0.5);
+                    Arc::new(Metal::new(albedo, fuzz))
                 } else {
-                    Rc::new(Dielectric::new(1.5))
+                    Arc::new(Dielectric::new(1.5))
                 }
             } else {
                 continue;
             };
 
-            world.add(Rc::new(Sphere::new(center, 0.2, sphere_mat)));
+            world.add(Arc::new(Sphere::new(center, 0.2, sphere_mat)));
         }
     }
 
-    let mat1 = Rc::new(Dielectric::new(1.5));
-    world.add(Rc::new(
+    let mat1 = Arc::new(Dielectric::new(1.5));
+    world.add(Arc::new(
         Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, mat1),
     ));
 
-    let mat2 = Rc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
-    world.add(Rc::new(
+    let mat2 = Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
+    world.add(Arc::new(
         Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, mat2),
     ));
 
-    let mat3 = Rc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
-    world.add(Rc::new(
+    let mat3 = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
+    world.add(Arc::new(
         Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, mat3),
     ));
 
-    let mut cam = Camera::default();
-
-    cam.render(&world);
+    world
 }
@@ -1,64 +1,270 @@
 use crate::vec3::{dot, random_unit_vector, reflect, refract, unit_vector};
-use crate::{color::Color, hit::HitRecord, ray::Ray};
-use rand::Rng;
+use crate::microfacet::{fresnel_conductor, TrowbridgeReitz};
+use crate::onb::Onb;
+use crate::pdf::{CosinePdf, MicrofacetPdf, PhasePdf, Pdf, SpherePdf};
+use crate::phase::{HenyeyGreenstein, IsotropicPhase, PhaseFunction};
+use crate::texture::{SolidColor, Texture};
+use crate::{color::Color, hit::HitRecord, ray::Ray, sampler::Sampler};
+use std::f64::consts::PI;
+use std::sync::Arc;
 
-pub struct ScatterResult {
-    pub attenuation: Color,
-    pub scattered: Ray,
+/// What [`Material::scatter`] did with an incoming ray.
+///
+/// This replaces the earlier struct with a `specular` flag and an optional
+/// `pdf`: match on the variant instead of testing the flag. A sampled result
+/// carries no attenuation, since [`Material::bsdf`] gives the whole
+/// reflectance.
+pub enum ScatterResult {
+    /// `scattered` is the only direction the material could have picked,
+    /// as for a mirror or a refraction. Such a delta distribution has no
+    /// finite density, so the renderer follows `scattered` with weight
+    /// `attenuation` instead of weighting by a pdf.
+    Specular { attenuation: Color, scattered: Ray },
+    /// `scattered` was drawn from `pdf`, and the renderer weights it by
+    /// [`Material::bsdf`] over `pdf`'s value.
+    Sampled { scattered: Ray, pdf: Box<dyn Pdf> },
 }
 
-pub trait Material {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterResult>;
+impl ScatterResult {
+    pub fn specular(attenuation: Color, scattered: Ray) -> Self {
+        ScatterResult::Specular {
+            attenuation,
+            scattered,
+        }
+    }
+
+    pub fn sampled(scattered: Ray, pdf: Box<dyn Pdf>) -> Self {
+        ScatterResult::Sampled { scattered, pdf }
+    }
+}
+
+pub trait Material: Send + Sync {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut Sampler,
+    ) -> Option<ScatterResult>;
+
+    /// The BSDF times the cosine between `scattered` and the normal: how
+    /// much of the light arriving back along `scattered`, per unit solid
+    /// angle, the surface sends out along `ray_in`. Specular materials
+    /// never need it.
+    ///
+    /// This replaces `scattering_pdf`: a material whose `scatter` returned
+    /// the albedo `a` and whose `scattering_pdf` returned `p` returns `a * p`
+    /// here.
+    fn bsdf(&self, _ray_in: &Ray, _hit_record: &HitRecord, _scattered: &Ray) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// Radiance the surface emits toward `ray_in` at the hit point.
+    fn emitted(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// Whether [`Material::emitted`] can be non-zero, making surfaces of
+    /// this material worth sampling as lights.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// For the phase-function materials of participating media, the
+    /// single-scattering albedo and phase function at the hit point. The
+    /// renderer scatters these through the phase function instead of
+    /// [`Material::scatter`], since there is no surface at the point.
+    fn volume_scattering(&self, _hit_record: &HitRecord) -> Option<VolumeScatter<'_>> {
+        None
+    }
+}
+
+pub struct VolumeScatter<'a> {
+    pub albedo: Color,
+    pub phase: &'a dyn PhaseFunction,
 }
 
 pub struct Lambertian {
-    albedo: Color,
+    tex: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Color) -> Self {
-        Lambertian { albedo }
+        Lambertian::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Lambertian { tex }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterResult> {
-        let mut scatter_direction = hit_record.normal + random_unit_vector();
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut Sampler,
+    ) -> Option<ScatterResult> {
+        let pdf = CosinePdf::new(hit_record.normal);
+        let scatter_direction = pdf.generate(sampler);
 
-        if scatter_direction.near_zero() {
-            scatter_direction = hit_record.normal;
-        }
+        Some(ScatterResult::sampled(
+            Ray::with_time(hit_record.p, scatter_direction, ray_in.time()),
+            Box::new(pdf),
+        ))
+    }
 
-        Some(ScatterResult {
-            attenuation: self.albedo,
-            scattered: Ray::new(hit_record.p, scatter_direction),
-        })
+    fn bsdf(&self, _ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        let cos_theta = dot(hit_record.normal, unit_vector(scattered.direction()));
+        self.tex.value(hit_record.u, hit_record.v, &hit_record.p) * (cos_theta / PI).max(0.0)
     }
 }
 
+/// A mirror whose reflections are blurred by `fuzz`, from 0 to 1: each
+/// reflected ray is jittered by up to `fuzz` toward a random direction.
+///
+/// The jitter has no density the renderer could evaluate, so even a fuzzy
+/// reflection is returned as [`ScatterResult::Specular`], a delta lobe in a
+/// randomly perturbed direction. Lights are then only found by the paths
+/// that happen to bounce into them, never sampled directly, so small lights
+/// seen in fuzzy metal stay noisy. [`Conductor`] is the rough metal that
+/// does take part in light sampling.
 pub struct Metal {
-    albedo: Color,
+    tex: Arc<dyn Texture>,
     fuzz: f64,
 }
 
 impl Metal {
     pub fn new(albedo: Color, fuzz: f64) -> Self {
+        Metal::from_texture(Arc::new(SolidColor::new(albedo)), fuzz)
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>, fuzz: f64) -> Self {
         Metal {
-            albedo,
+            tex,
             fuzz: if fuzz < 1.0 { fuzz } else { 1.0 },
         }
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterResult> {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut Sampler,
+    ) -> Option<ScatterResult> {
         let mut reflected = reflect(ray_in.direction(), hit_record.normal);
-        reflected = unit_vector(reflected) + (self.fuzz * random_unit_vector());
+        reflected = unit_vector(reflected) + (self.fuzz * random_unit_vector(sampler));
 
-        Some(ScatterResult {
-            attenuation: self.albedo,
-            scattered: Ray::new(hit_record.p, reflected),
-        })
+        Some(ScatterResult::specular(
+            self.tex.value(hit_record.u, hit_record.v, &hit_record.p),
+            Ray::with_time(hit_record.p, reflected, ray_in.time()),
+        ))
+    }
+}
+
+/// A complex index of refraction `eta + i k` for each color channel, the
+/// optical constants of a metal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComplexIor {
+    pub eta: Color,
+    pub k: Color,
+}
+
+impl ComplexIor {
+    pub const GOLD: ComplexIor = ComplexIor {
+        eta: Color::new(0.143, 0.374, 1.442),
+        k: Color::new(3.983, 2.385, 1.603),
+    };
+    pub const COPPER: ComplexIor = ComplexIor {
+        eta: Color::new(0.200, 0.924, 1.102),
+        k: Color::new(3.912, 2.452, 2.142),
+    };
+    pub const ALUMINIUM: ComplexIor = ComplexIor {
+        eta: Color::new(1.657, 0.880, 0.521),
+        k: Color::new(9.224, 6.270, 4.837),
+    };
+}
+
+/// A rough metal: the Cook-Torrance microfacet BRDF with the GGX
+/// distribution and the exact Fresnel reflectance of a conductor. Unlike
+/// [`Metal`]'s fuzz it never reflects more light than arrives, and its color
+/// comes from measured optical constants.
+///
+/// Roughness runs from 0, a mirror, to 1, and can differ between the two
+/// directions along the surface to give the stretched highlights of brushed
+/// metal: `roughness_u` applies along the direction in which the surface's
+/// `u` texture coordinate increases, and `roughness_v` across it. Surfaces
+/// too smooth for the distribution to resolve reflect as a perfect mirror.
+pub struct Conductor {
+    ior: ComplexIor,
+    distribution: TrowbridgeReitz,
+}
+
+impl Conductor {
+    pub fn new(ior: ComplexIor, roughness: f64) -> Self {
+        Conductor::anisotropic(ior, roughness, roughness)
+    }
+
+    pub fn anisotropic(ior: ComplexIor, roughness_u: f64, roughness_v: f64) -> Self {
+        Conductor {
+            ior,
+            distribution: TrowbridgeReitz::new(
+                TrowbridgeReitz::roughness_to_alpha(roughness_u),
+                TrowbridgeReitz::roughness_to_alpha(roughness_v),
+            ),
+        }
+    }
+
+    fn frame(hit_record: &HitRecord) -> Onb {
+        Onb::with_tangent(hit_record.normal, hit_record.tangent)
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut Sampler,
+    ) -> Option<ScatterResult> {
+        let frame = Conductor::frame(hit_record);
+        let wo = frame.to_local(-unit_vector(ray_in.direction()));
+        if wo.z() <= 0.0 {
+            return None;
+        }
+        if self.distribution.effectively_smooth() {
+            let fresnel = fresnel_conductor(wo.z(), self.ior.eta, self.ior.k);
+            let reflected = reflect(unit_vector(ray_in.direction()), hit_record.normal);
+            return Some(ScatterResult::specular(
+                fresnel,
+                Ray::with_time(hit_record.p, reflected, ray_in.time()),
+            ));
+        }
+        let pdf = MicrofacetPdf::new(frame, self.distribution, wo);
+        let direction = pdf.generate(sampler);
+        // Light reflected into the surface is lost.
+        if dot(direction, hit_record.normal) <= 0.0 {
+            return None;
+        }
+        Some(ScatterResult::sampled(
+            Ray::with_time(hit_record.p, direction, ray_in.time()),
+            Box::new(pdf),
+        ))
+    }
+
+    fn bsdf(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        let frame = Conductor::frame(hit_record);
+        let wo = frame.to_local(-unit_vector(ray_in.direction()));
+        let wi = frame.to_local(unit_vector(scattered.direction()));
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let h = unit_vector(wo + wi);
+        let fresnel = fresnel_conductor(dot(wo, h), self.ior.eta, self.ior.k);
+        // f = F D G / (4 cos_o cos_i), times cos_i.
+        let d = self.distribution.d(h);
+        fresnel * (d * self.distribution.g(wo, wi) / (4.0 * wo.z()))
     }
 }
 
@@ -73,7 +279,12 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterResult> {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut Sampler,
+    ) -> Option<ScatterResult> {
         let attenuation = Color::new(1.0, 1.0, 1.0);
         let ri = if hit_record.front_face {
             1.0 / self.refraction_index
@@ -86,21 +297,136 @@ impl Material for Dielectric {
         let sin_theta = (1.0_f64 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = ri * sin_theta > 1.0;
-        let mut rng = rand::rng();
-        let direction = if cannot_refract || reflectance(cos_theta, ri) > rng.random_range(0.0..1.0) {
+        let direction = if cannot_refract || reflectance(cos_theta, ri) > sampler.random() {
             reflect(unit_direction, hit_record.normal)
         } else {
             refract(unit_direction, hit_record.normal, ri)
         };
 
-        let scattered = Ray::new(hit_record.p, direction);
+        let scattered = Ray::with_time(hit_record.p, direction, ray_in.time());
+
+        Some(ScatterResult::specular(attenuation, scattered))
+    }
+}
+
+pub struct DiffuseLight {
+    emit: Color,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        DiffuseLight { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _ray_in: &Ray,
+        _hit_record: &HitRecord,
+        _sampler: &mut Sampler,
+    ) -> Option<ScatterResult> {
+        None
+    }
 
-        Some(ScatterResult {
-            attenuation: attenuation,
-            scattered: scattered,
+    fn emitted(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Color {
+        self.emit
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+}
+
+/// Scatters uniformly in every direction; the phase function of a
+/// [`ConstantMedium`](crate::medium::ConstantMedium).
+pub struct Isotropic {
+    tex: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Color) -> Self {
+        Isotropic::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Isotropic { tex }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut Sampler,
+    ) -> Option<ScatterResult> {
+        let direction = SpherePdf.generate(sampler);
+        Some(ScatterResult::sampled(
+            Ray::with_time(hit_record.p, direction, ray_in.time()),
+            Box::new(SpherePdf),
+        ))
+    }
+
+    fn bsdf(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        let albedo = self.tex.value(hit_record.u, hit_record.v, &hit_record.p);
+        albedo * IsotropicPhase.p(ray_in.direction(), scattered.direction())
+    }
+
+    fn volume_scattering(&self, hit_record: &HitRecord) -> Option<VolumeScatter<'_>> {
+        Some(VolumeScatter {
+            albedo: self.tex.value(hit_record.u, hit_record.v, &hit_record.p),
+            phase: &IsotropicPhase,
         })
     }
+}
+
+/// A phase-function material for media that scatter preferentially forward
+/// or back, following [`HenyeyGreenstein`] with asymmetry `g`.
+pub struct Anisotropic {
+    tex: Arc<dyn Texture>,
+    phase: HenyeyGreenstein,
+}
+
+impl Anisotropic {
+    pub fn new(albedo: Color, g: f64) -> Self {
+        Anisotropic::from_texture(Arc::new(SolidColor::new(albedo)), g)
+    }
 
+    pub fn from_texture(tex: Arc<dyn Texture>, g: f64) -> Self {
+        Anisotropic {
+            tex,
+            phase: HenyeyGreenstein::new(g),
+        }
+    }
+}
+
+impl Material for Anisotropic {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut Sampler,
+    ) -> Option<ScatterResult> {
+        let pdf = PhasePdf::new(self.phase, ray_in.direction());
+        let direction = pdf.generate(sampler);
+        Some(ScatterResult::sampled(
+            Ray::with_time(hit_record.p, direction, ray_in.time()),
+            Box::new(pdf),
+        ))
+    }
+
+    fn bsdf(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Color {
+        let albedo = self.tex.value(hit_record.u, hit_record.v, &hit_record.p);
+        albedo * self.phase.p(ray_in.direction(), scattered.direction())
+    }
+
+    fn volume_scattering(&self, hit_record: &HitRecord) -> Option<VolumeScatter<'_>> {
+        Some(VolumeScatter {
+            albedo: self.tex.value(hit_record.u, hit_record.v, &hit_record.p),
+            phase: &self.phase,
+        })
+    }
 }
 
 fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
@@ -108,3 +434,70 @@ fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
     r0 = r0 * r0;
     r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hit::Hittable;
+    use crate::interval::Interval;
+    use crate::matrix::Mat4;
+    use crate::quad::Quad;
+    use crate::transform::RotateY;
+    use crate::vec3::{Point3, Vec3};
+
+    #[test]
+    fn conductor_anisotropy_turns_with_the_surface() {
+        // A brushed plate and the same plate turned a quarter about y, lit
+        // and seen from directions turned the same way, must look the same.
+        let brushed = Arc::new(Conductor::anisotropic(ComplexIor::GOLD, 0.2, 0.7));
+        let plate: Arc<dyn Hittable> = Arc::new(Quad::new(
+            Point3::new(-1.0, 0.0, -1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            brushed,
+        ));
+        let turned = RotateY::new(plate.clone(), 90.0);
+        let rotation = Mat4::rotation_y(90.0);
+
+        let origin = Point3::new(0.6, 1.0, 0.2);
+        let ray_in = Ray::new(origin, -origin);
+        let scattered = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(-0.3, 1.0, 0.5));
+        let turn = |r: &Ray| {
+            Ray::new(rotation.transform_point(r.origin()), rotation.transform_vector(r.direction()))
+        };
+        let (turned_in, turned_out) = (turn(&ray_in), turn(&scattered));
+
+        let rec = plate.hit(&ray_in, Interval::new(0.001, f64::INFINITY)).unwrap();
+        let turned_rec = turned.hit(&turned_in, Interval::new(0.001, f64::INFINITY)).unwrap();
+        let value = rec.mat.bsdf(&ray_in, &rec, &scattered);
+        let turned_value = turned_rec.mat.bsdf(&turned_in, &turned_rec, &turned_out);
+        assert!(value.x() > 0.0);
+        assert!((value - turned_value).length() < 1e-9, "{value:?} vs {turned_value:?}");
+
+        // Swapping the roughnesses is what turning the lobe alone would do.
+        let swapped = Conductor::anisotropic(ComplexIor::GOLD, 0.7, 0.2);
+        assert!((swapped.bsdf(&ray_in, &rec, &scattered) - value).length() > 1e-3);
+    }
+
+    #[test]
+    fn smooth_conductors_reflect_as_mirrors() {
+        let plate = Quad::new(
+            Point3::new(-1.0, 0.0, -1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            Arc::new(Conductor::new(ComplexIor::GOLD, 0.0)),
+        );
+        let ray_in = Ray::new(Point3::new(-1.0, 2.0, 0.0), Vec3::new(1.0, -2.0, 0.0));
+        let rec = plate.hit(&ray_in, Interval::new(0.001, f64::INFINITY)).unwrap();
+        let cos_theta = 2.0 / 5.0_f64.sqrt();
+        match rec.mat.scatter(&ray_in, &rec, &mut Sampler::new(1)) {
+            Some(ScatterResult::Specular { attenuation, scattered }) => {
+                let fresnel = fresnel_conductor(cos_theta, ComplexIor::GOLD.eta, ComplexIor::GOLD.k);
+                assert!((attenuation - fresnel).length() < 1e-12);
+                let mirrored = unit_vector(Vec3::new(1.0, 2.0, 0.0));
+                assert!((unit_vector(scattered.direction()) - mirrored).length() < 1e-12);
+            }
+            _ => panic!("expected a specular reflection"),
+        }
+    }
+}
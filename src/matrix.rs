@@ -0,0 +1,202 @@
+use crate::vec3::{Point3, Vec3};
+use std::ops::Mul;
+
+/// A row-major 4x4 matrix acting on column vectors, used for affine
+/// transforms of points and directions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub const fn new(m: [[f64; 4]; 4]) -> Self {
+        Mat4 { m }
+    }
+
+    pub const fn identity() -> Self {
+        Mat4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut result = Mat4::identity();
+        for axis in 0..3 {
+            result.m[axis][3] = offset[axis];
+        }
+        result
+    }
+
+    pub fn scaling(factors: Vec3) -> Self {
+        let mut result = Mat4::identity();
+        for axis in 0..3 {
+            result.m[axis][axis] = factors[axis];
+        }
+        result
+    }
+
+    /// Counter-clockwise rotation about the x axis, looking down the axis
+    /// toward the origin. Angles are in degrees.
+    pub fn rotation_x(degrees: f64) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Mat4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, cos, -sin, 0.0],
+            [0.0, sin, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotation_y(degrees: f64) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Mat4::new([
+            [cos, 0.0, sin, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [-sin, 0.0, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotation_z(degrees: f64) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Mat4::new([
+            [cos, -sin, 0.0, 0.0],
+            [sin, cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut result = Mat4::identity();
+        for (i, row) in result.m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        result
+    }
+
+    /// True when the bottom row is `[0, 0, 0, 1]`, so points need no
+    /// perspective divide.
+    pub fn is_affine(&self) -> bool {
+        self.m[3] == [0.0, 0.0, 0.0, 1.0]
+    }
+
+    /// The inverse by Gauss-Jordan elimination with partial pivoting, or
+    /// `None` if the matrix is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Mat4::identity().m;
+
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for k in 0..4 {
+                a[col][k] *= scale;
+                inv[col][k] *= scale;
+            }
+            for row in 0..4 {
+                let factor = a[row][col];
+                if row != col && factor != 0.0 {
+                    for k in 0..4 {
+                        a[row][k] -= factor * a[col][k];
+                        inv[row][k] -= factor * inv[col][k];
+                    }
+                }
+            }
+        }
+
+        Some(Mat4::new(inv))
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        Point3::new(
+            m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3],
+            m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3],
+            m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3],
+        )
+    }
+
+    /// Transforms a direction, ignoring the translation column.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Mat4::identity()
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    /// `a * b` applies `b` first, then `a`.
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut result = [[0.0; 4]; 4];
+        for (i, row) in result.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Mat4::new(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Mat4, b: Mat4) {
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((a.m[i][j] - b.m[i][j]).abs() < 1e-12, "{a:?} != {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn inverse_undoes_the_transform() {
+        let m = Mat4::translation(Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotation_y(30.0)
+            * Mat4::rotation_x(-75.0)
+            * Mat4::scaling(Vec3::new(2.0, 0.5, -3.0));
+        let inverse = m.inverse().unwrap();
+        assert_near(m * inverse, Mat4::identity());
+        assert_near(inverse * m, Mat4::identity());
+
+        assert!(Mat4::scaling(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn rotations_are_counter_clockwise() {
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        let near = |a: Vec3, b: Vec3| (a - b).length() < 1e-12;
+        assert!(near(Mat4::rotation_x(90.0).transform_vector(y), z));
+        assert!(near(Mat4::rotation_y(90.0).transform_vector(z), x));
+        assert!(near(Mat4::rotation_z(90.0).transform_vector(x), y));
+
+        let t = Mat4::translation(Vec3::new(5.0, 0.0, 0.0));
+        assert!(near(t.transform_point(y), Vec3::new(5.0, 1.0, 0.0)));
+        assert!(near(t.transform_vector(y), y));
+    }
+}
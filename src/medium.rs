@@ -0,0 +1,336 @@
+mod grid;
+
+pub use grid::VoxelGrid;
+
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    hit::{HitRecord, Hittable},
+    interval::Interval,
+    material::{Isotropic, Material},
+    perlin::Perlin,
+    ray::Ray,
+    sampler::Sampler,
+    texture::Texture,
+    vec3::{Point3, Vec3},
+};
+use std::sync::Arc;
+
+/// A volume of uniform density filling a closed boundary, such as smoke or
+/// fog. A ray passing through scatters after an exponentially distributed
+/// free-flight distance, or passes straight through if that distance is
+/// longer than its path inside.
+///
+/// The boundary must be convex: a ray is assumed to enter and leave it at
+/// most once.
+pub struct ConstantMedium {
+    boundary: Arc<dyn Hittable>,
+    inv_density: f64,
+    phase_function: Arc<dyn Material>,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Hittable>, density: f64, albedo: Color) -> Self {
+        ConstantMedium::with_phase_function(boundary, density, Arc::new(Isotropic::new(albedo)))
+    }
+
+    pub fn from_texture(boundary: Arc<dyn Hittable>, density: f64, tex: Arc<dyn Texture>) -> Self {
+        let phase_function = Arc::new(Isotropic::from_texture(tex));
+        ConstantMedium::with_phase_function(boundary, density, phase_function)
+    }
+
+    pub fn with_phase_function(
+        boundary: Arc<dyn Hittable>,
+        density: f64,
+        phase_function: Arc<dyn Material>,
+    ) -> Self {
+        ConstantMedium {
+            boundary,
+            inv_density: 1.0 / density,
+            phase_function,
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (t_enter, t_exit) = boundary_span(self.boundary.as_ref(), r, ray_t)?;
+
+        let ray_length = r.direction().length();
+        let distance_inside_boundary = (t_exit - t_enter) * ray_length;
+        let mut sampler = Sampler::for_ray(r);
+        let hit_distance = self.inv_density * free_flight(&mut sampler);
+        if hit_distance > distance_inside_boundary {
+            return None;
+        }
+
+        let t = t_enter + hit_distance / ray_length;
+        Some(scattering_record(r, t, &self.phase_function))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    fn shadow_hit(&self, _r: &Ray, _ray_t: Interval) -> Option<HitRecord> {
+        None
+    }
+
+    /// Exact, by the Beer-Lambert law, since the density is uniform.
+    fn transmittance(&self, r: &Ray, ray_t: Interval, _sampler: &mut Sampler) -> f64 {
+        let Some((t_enter, t_exit)) = boundary_span(self.boundary.as_ref(), r, ray_t) else {
+            return 1.0;
+        };
+        let distance_inside_boundary = (t_exit - t_enter) * r.direction().length();
+        (-distance_inside_boundary / self.inv_density).exp()
+    }
+}
+
+/// A spatially varying density, in collisions per unit length.
+pub trait DensityField: Send + Sync {
+    fn density(&self, p: &Point3) -> f64;
+
+    /// An upper bound on [`DensityField::density`] everywhere; the majorant
+    /// that delta and ratio tracking sample against.
+    fn max_density(&self) -> f64;
+}
+
+/// Procedural wispy density: turbulence from seeded Perlin noise, scaled to
+/// peak at `max_density`.
+pub struct NoiseDensity {
+    noise: Perlin,
+    scale: f64,
+    max_density: f64,
+}
+
+impl NoiseDensity {
+    pub fn new(max_density: f64, scale: f64, seed: u64) -> Self {
+        NoiseDensity {
+            noise: Perlin::new(seed),
+            scale,
+            max_density: max_density.max(0.0),
+        }
+    }
+}
+
+impl DensityField for NoiseDensity {
+    fn density(&self, p: &Point3) -> f64 {
+        // Turbulence can slightly exceed 1, so clamp to keep the majorant
+        // a true bound.
+        self.max_density * self.noise.turb(&(self.scale * *p), 7).min(1.0)
+    }
+
+    fn max_density(&self) -> f64 {
+        self.max_density
+    }
+}
+
+/// A medium whose density varies through space, such as smoke from a
+/// simulation or procedural clouds, filling a convex `boundary`.
+///
+/// Collisions are found with delta tracking: tentative collisions are drawn
+/// against the constant majorant and each is kept as a real scattering event
+/// with probability `density / majorant`, which samples the true free-flight
+/// distribution without bias however the density varies. Shadow rays pass
+/// through instead, and are attenuated by a ratio-tracking estimate of the
+/// transmittance.
+pub struct HeterogeneousMedium {
+    boundary: Arc<dyn Hittable>,
+    field: Arc<dyn DensityField>,
+    phase_function: Arc<dyn Material>,
+}
+
+impl HeterogeneousMedium {
+    pub fn new(boundary: Arc<dyn Hittable>, field: Arc<dyn DensityField>, albedo: Color) -> Self {
+        let phase_function = Arc::new(Isotropic::new(albedo));
+        HeterogeneousMedium::with_phase_function(boundary, field, phase_function)
+    }
+
+    pub fn with_phase_function(
+        boundary: Arc<dyn Hittable>,
+        field: Arc<dyn DensityField>,
+        phase_function: Arc<dyn Material>,
+    ) -> Self {
+        HeterogeneousMedium {
+            boundary,
+            field,
+            phase_function,
+        }
+    }
+}
+
+impl Hittable for HeterogeneousMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let majorant = self.field.max_density();
+        let (t_enter, t_exit) = boundary_span(self.boundary.as_ref(), r, ray_t)?;
+        if majorant <= 0.0 {
+            return None;
+        }
+
+        let step = 1.0 / (majorant * r.direction().length());
+        let mut sampler = Sampler::for_ray(r);
+        let mut t = t_enter;
+        loop {
+            t += step * free_flight(&mut sampler);
+            if t >= t_exit {
+                return None;
+            }
+            if sampler.random() * majorant < self.field.density(&r.at(t)) {
+                return Some(scattering_record(r, t, &self.phase_function));
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    fn shadow_hit(&self, _r: &Ray, _ray_t: Interval) -> Option<HitRecord> {
+        None
+    }
+
+    /// An unbiased estimate of the fraction of light that crosses the medium
+    /// along `r` within `ray_t`, by ratio tracking: every tentative
+    /// collision scales the estimate by the chance it was a null collision.
+    fn transmittance(&self, r: &Ray, ray_t: Interval, sampler: &mut Sampler) -> f64 {
+        let majorant = self.field.max_density();
+        let Some((t_enter, t_exit)) = boundary_span(self.boundary.as_ref(), r, ray_t) else {
+            return 1.0;
+        };
+        if majorant <= 0.0 {
+            return 1.0;
+        }
+
+        let step = 1.0 / (majorant * r.direction().length());
+        let mut transmittance = 1.0;
+        let mut t = t_enter;
+        loop {
+            t += step * free_flight(sampler);
+            if t >= t_exit {
+                return transmittance;
+            }
+            transmittance *= 1.0 - self.field.density(&r.at(t)) / majorant;
+        }
+    }
+}
+
+/// The part of `ray_t` that lies inside `boundary`. The entry is searched
+/// for along the whole line, not just `ray_t`, so a ray that starts inside
+/// the boundary still finds where it entered.
+fn boundary_span(boundary: &dyn Hittable, r: &Ray, ray_t: Interval) -> Option<(f64, f64)> {
+    let entry = boundary.hit(r, Interval::universe())?;
+    let exit = boundary.hit(r, Interval::new(entry.t + 0.0001, f64::INFINITY))?;
+
+    let t_enter = entry.t.max(ray_t.min).max(0.0);
+    let t_exit = exit.t.min(ray_t.max);
+    (t_enter < t_exit).then_some((t_enter, t_exit))
+}
+
+/// An exponentially distributed distance, in mean free paths.
+fn free_flight(sampler: &mut Sampler) -> f64 {
+    // 1 - random() lies in (0, 1], so the logarithm is finite.
+    -(1.0 - sampler.random()).ln()
+}
+
+fn scattering_record(r: &Ray, t: f64, phase_function: &Arc<dyn Material>) -> HitRecord {
+    HitRecord {
+        p: r.at(t),
+        // Phase functions ignore the normal and facing.
+        normal: Vec3::new(1.0, 0.0, 0.0),
+        t,
+        u: 0.0,
+        v: 0.0,
+        tangent: Vec3::new(0.0, 0.0, 0.0),
+        front_face: true,
+        mat: phase_function.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::vec3::Point3;
+
+    fn fog(density: f64) -> ConstantMedium {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let boundary = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, mat));
+        ConstantMedium::new(boundary, density, Color::new(0.9, 0.9, 0.9))
+    }
+
+    #[test]
+    fn rays_starting_inside_scatter_within_the_boundary() {
+        let medium = fog(1e6);
+        let r = Ray::new(Point3::new(0.2, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = medium.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+        assert!(rec.t > 0.001 && rec.t < 0.01, "t = {}", rec.t);
+    }
+
+    #[test]
+    fn transmission_follows_beer_lambert() {
+        // The diameter is 2, so a fraction exp(-2 * density) passes through.
+        let density = 0.5;
+        let medium = fog(density);
+        let trials = 20_000;
+        let passed = (0..trials)
+            .filter(|&i| {
+                let origin = Point3::new(0.0, 0.0, 5.0 + i as f64 * 1e-4);
+                let r = Ray::new(origin, Vec3::new(0.0, 0.0, -1.0));
+                medium.hit(&r, Interval::new(0.001, f64::INFINITY)).is_none()
+            })
+            .count();
+        let expected = (-2.0 * density).exp();
+        let measured = passed as f64 / trials as f64;
+        assert!((measured - expected).abs() < 0.02, "{measured} vs {expected}");
+    }
+
+    /// A uniform field with a loose majorant, so tracking has to reject
+    /// half of its tentative collisions.
+    struct Uniform(f64);
+
+    impl DensityField for Uniform {
+        fn density(&self, _p: &Point3) -> f64 {
+            self.0
+        }
+
+        fn max_density(&self) -> f64 {
+            2.0 * self.0
+        }
+    }
+
+    #[test]
+    fn delta_and_ratio_tracking_match_beer_lambert() {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let boundary = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, mat));
+        let medium = HeterogeneousMedium::new(boundary, Arc::new(Uniform(0.5)), Color::new(1.0, 1.0, 1.0));
+        let expected = (-1.0_f64).exp();
+
+        let trials = 20_000;
+        let mut sampler = Sampler::new(11);
+        let mut passed = 0;
+        let mut ratio_sum = 0.0;
+        for i in 0..trials {
+            let origin = Point3::new(0.0, 0.0, 5.0 + i as f64 * 1e-4);
+            let r = Ray::new(origin, Vec3::new(0.0, 0.0, -1.0));
+            let ray_t = Interval::new(0.001, f64::INFINITY);
+            if medium.hit(&r, ray_t).is_none() {
+                passed += 1;
+            }
+            ratio_sum += medium.transmittance(&r, ray_t, &mut sampler);
+        }
+
+        let delta = passed as f64 / trials as f64;
+        let ratio = ratio_sum / trials as f64;
+        assert!((delta - expected).abs() < 0.02, "delta tracking {delta} vs {expected}");
+        assert!((ratio - expected).abs() < 0.01, "ratio tracking {ratio} vs {expected}");
+
+        // Starting at the center only half the diameter is left to cross.
+        let inside = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let estimate = (0..trials)
+            .map(|_| medium.transmittance(&inside, Interval::new(0.001, f64::INFINITY), &mut sampler))
+            .sum::<f64>()
+            / trials as f64;
+        assert!((estimate - (-0.5_f64).exp()).abs() < 0.01, "{estimate}");
+    }
+}
@@ -0,0 +1,205 @@
+use super::DensityField;
+use crate::aabb::Aabb;
+use crate::vec3::Point3;
+use std::io;
+use std::path::Path;
+
+/// Densities sampled on a dense grid of voxels spanning a box, interpolated
+/// trilinearly between voxel centers and zero outside the box.
+///
+/// Grid files are plain text. A `voxels NX NY NZ` line gives the resolution,
+/// an optional `bounds MINX MINY MINZ MAXX MAXY MAXZ` line the box (the unit
+/// cube by default), and the remaining `NX * NY * NZ` numbers the
+/// densities, with x varying fastest and z slowest. `#` starts a comment.
+pub struct VoxelGrid {
+    resolution: [usize; 3],
+    min: Point3,
+    max: Point3,
+    data: Vec<f64>,
+    max_density: f64,
+}
+
+impl VoxelGrid {
+    /// # Panics
+    ///
+    /// Panics if `data` does not hold one density per voxel, if any
+    /// dimension is zero, or if `min` is not below `max` on every axis.
+    pub fn new(resolution: [usize; 3], min: Point3, max: Point3, data: Vec<f64>) -> Self {
+        assert!(resolution.iter().all(|&n| n > 0), "voxel grid resolution must be non-zero");
+        assert_eq!(
+            data.len(),
+            resolution.iter().product::<usize>(),
+            "voxel grid needs one density per voxel"
+        );
+        assert!(
+            (0..3).all(|axis| min[axis] < max[axis]),
+            "voxel grid bounds must have positive size"
+        );
+
+        let max_density = data.iter().copied().fold(0.0, f64::max);
+        VoxelGrid {
+            resolution,
+            min,
+            max,
+            data,
+            max_density,
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        VoxelGrid::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(src: &str) -> io::Result<Self> {
+        let mut resolution = None;
+        let mut bounds = (Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        let mut data = Vec::new();
+
+        for (index, raw_line) in src.lines().enumerate() {
+            let line = index + 1;
+            let error = |message: String| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {line}: {message}"))
+            };
+            let content = raw_line.split('#').next().unwrap_or("");
+            let tokens: Vec<&str> = content.split_whitespace().collect();
+            let numbers = |tokens: &[&str]| {
+                tokens
+                    .iter()
+                    .map(|t| match t.parse::<f64>() {
+                        Ok(n) if n.is_finite() => Ok(n),
+                        _ => Err(error(format!("invalid number '{t}'"))),
+                    })
+                    .collect::<io::Result<Vec<f64>>>()
+            };
+
+            match tokens.first() {
+                None => {}
+                Some(&"voxels") => {
+                    let dims = tokens[1..]
+                        .iter()
+                        .map(|t| t.parse::<usize>().ok().filter(|&n| n > 0))
+                        .collect::<Option<Vec<_>>>();
+                    match dims.as_deref() {
+                        Some(&[nx, ny, nz]) if resolution.is_none() && data.is_empty() => {
+                            resolution = Some([nx, ny, nz]);
+                        }
+                        Some(&[_, _, _]) => {
+                            return Err(error("voxels must come once, before the densities".into()));
+                        }
+                        _ => return Err(error("voxels expects 3 positive integers".into())),
+                    }
+                }
+                Some(&"bounds") => match *numbers(&tokens[1..])?.as_slice() {
+                    [x0, y0, z0, x1, y1, z1] if x0 < x1 && y0 < y1 && z0 < z1 => {
+                        bounds = (Point3::new(x0, y0, z0), Point3::new(x1, y1, z1));
+                    }
+                    [_, _, _, _, _, _] => {
+                        return Err(error("bounds minimum must be below the maximum".into()));
+                    }
+                    _ => return Err(error("bounds expects 6 numbers".into())),
+                },
+                Some(_) => {
+                    if resolution.is_none() {
+                        return Err(error("densities before the voxels line".into()));
+                    }
+                    for density in numbers(&tokens)? {
+                        if density < 0.0 {
+                            return Err(error(format!("negative density {density}")));
+                        }
+                        data.push(density);
+                    }
+                }
+            }
+        }
+
+        let resolution = resolution.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "missing voxels line")
+        })?;
+        let expected: usize = resolution.iter().product();
+        if data.len() != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected {expected} densities, found {}", data.len()),
+            ));
+        }
+        Ok(VoxelGrid::new(resolution, bounds.0, bounds.1, data))
+    }
+
+    /// The box the grid spans, a natural boundary for a medium using it.
+    pub fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(self.min, self.max)
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f64 {
+        let [nx, ny, _] = self.resolution;
+        self.data[(z * ny + y) * nx + x]
+    }
+}
+
+impl DensityField for VoxelGrid {
+    fn density(&self, p: &Point3) -> f64 {
+        let mut base = [0; 3];
+        let mut frac = [0.0; 3];
+        for axis in 0..3 {
+            if !(self.min[axis]..=self.max[axis]).contains(&p[axis]) {
+                return 0.0;
+            }
+            // Voxel values sit at cell centers; clamp so the outer half
+            // cells take the edge value.
+            let n = self.resolution[axis];
+            let g = (p[axis] - self.min[axis]) / (self.max[axis] - self.min[axis]) * n as f64 - 0.5;
+            let g = g.clamp(0.0, (n - 1) as f64);
+            base[axis] = (g.floor() as usize).min(n.saturating_sub(2));
+            frac[axis] = g - base[axis] as f64;
+        }
+
+        let mut density = 0.0;
+        for corner in 0..8 {
+            let mut weight = 1.0;
+            let mut index = [0; 3];
+            for axis in 0..3 {
+                let high = corner & (1 << axis) != 0;
+                index[axis] = (base[axis] + high as usize).min(self.resolution[axis] - 1);
+                weight *= if high { frac[axis] } else { 1.0 - frac[axis] };
+            }
+            if weight > 0.0 {
+                density += weight * self.voxel(index[0], index[1], index[2]);
+            }
+        }
+        density
+    }
+
+    fn max_density(&self) -> f64 {
+        self.max_density
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_interpolates_between_voxel_centers() {
+        let grid = VoxelGrid::parse(
+            "# a 2x1x1 ramp\nvoxels 2 1 1\nbounds 0 0 0 2 1 1\n0 4 # densities\n",
+        )
+        .unwrap();
+        assert_eq!(grid.max_density(), 4.0);
+        let at = |x: f64| grid.density(&Point3::new(x, 0.5, 0.5));
+        assert_eq!(at(0.25), 0.0);
+        assert_eq!(at(1.0), 2.0);
+        assert_eq!(at(1.25), 3.0);
+        assert_eq!(at(1.9), 4.0);
+        assert_eq!(at(2.5), 0.0);
+    }
+
+    #[test]
+    fn reports_malformed_files_with_line_numbers() {
+        let err = |src: &str| VoxelGrid::parse(src).err().unwrap().to_string();
+        assert_eq!(err("voxels 2 2\n"), "line 1: voxels expects 3 positive integers");
+        assert_eq!(err("1 2 3\n"), "line 1: densities before the voxels line");
+        assert_eq!(err("voxels 1 1 2\n\n0.5 -1\n"), "line 3: negative density -1");
+        assert_eq!(err("voxels 1 1 2\n0.5\n"), "expected 2 densities, found 1");
+        assert_eq!(err("voxels 1 1 1\nbounds 0 0 0 1 1\n"), "line 2: bounds expects 6 numbers");
+    }
+}
@@ -0,0 +1,195 @@
+//! Microfacet models of rough surfaces, built from many tiny mirrors whose
+//! orientations follow a statistical distribution.
+//!
+//! Directions are in a local shading frame where +z is the surface normal
+//! and x and y are the two tangent directions, and all of them point away
+//! from the surface.
+
+use crate::color::Color;
+use crate::sampler::Sampler;
+use crate::vec3::{cross, unit_vector, Vec3};
+use std::f64::consts::PI;
+
+/// The smallest roughness modelled. Smoother surfaces have such a narrow
+/// distribution that floating point can no longer evaluate it reliably.
+const MIN_ALPHA: f64 = 1e-3;
+
+/// The Trowbridge-Reitz (GGX) distribution of microfacet normals, with
+/// roughness `alpha_x` along the local x axis and `alpha_y` along y.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrowbridgeReitz {
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha_x: f64, alpha_y: f64) -> Self {
+        TrowbridgeReitz {
+            alpha_x: alpha_x.max(MIN_ALPHA),
+            alpha_y: alpha_y.max(MIN_ALPHA),
+        }
+    }
+
+    /// Whether the distribution is too narrow to tell apart from a perfect
+    /// mirror, so surfaces with it are better treated as specular.
+    pub fn effectively_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) <= MIN_ALPHA
+    }
+
+    /// Maps perceptual roughness in [0, 1], which looks roughly linear, to
+    /// the distribution's alpha.
+    pub fn roughness_to_alpha(roughness: f64) -> f64 {
+        roughness.clamp(0.0, 1.0).powi(2)
+    }
+
+    /// Density of microfacet normal `h`, per unit of projected area.
+    pub fn d(&self, h: Vec3) -> f64 {
+        if h.z() <= 0.0 {
+            return 0.0;
+        }
+        let e = (h.x() / self.alpha_x).powi(2) + (h.y() / self.alpha_y).powi(2) + h.z().powi(2);
+        1.0 / (PI * self.alpha_x * self.alpha_y * e * e)
+    }
+
+    /// Smith's auxiliary function: the area of microfacets hidden from
+    /// direction `w`, relative to the visible area.
+    fn lambda(&self, w: Vec3) -> f64 {
+        let alpha2_tan2_theta = ((w.x() * self.alpha_x).powi(2) + (w.y() * self.alpha_y).powi(2))
+            / w.z().powi(2);
+        if !alpha2_tan2_theta.is_finite() {
+            return f64::INFINITY;
+        }
+        ((1.0 + alpha2_tan2_theta).sqrt() - 1.0) / 2.0
+    }
+
+    /// The fraction of microfacets visible from `w`.
+    pub fn g1(&self, w: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// The fraction of microfacets visible from both `wo` and `wi`, with
+    /// the two accounted for together rather than independently.
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density over solid angle of [`TrowbridgeReitz::sample_reflection`]
+    /// producing `wi` when seen from `wo`.
+    pub fn reflection_pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+        let h = unit_vector(wo + wi);
+        // The visible normal density G1(wo) max(0, wo.h) D(h) / wo.z, times
+        // the 1 / (4 wo.h) Jacobian of reflecting about h.
+        self.g1(wo) * self.d(h) / (4.0 * wo.z())
+    }
+
+    /// Reflects `wo` about a microfacet normal drawn from those visible from
+    /// `wo`, following Heitz's "Sampling the GGX Distribution of Visible
+    /// Normals". Some reflections point into the surface, and are only
+    /// valid as directions to give up on.
+    pub fn sample_reflection(&self, wo: Vec3, sampler: &mut Sampler) -> Vec3 {
+        // Stretch the view so the distribution becomes a hemisphere, and
+        // sample the part of it visible from there.
+        let vh = unit_vector(Vec3::new(self.alpha_x * wo.x(), self.alpha_y * wo.y(), wo.z()));
+        let len_squared = vh.x() * vh.x() + vh.y() * vh.y();
+        let t1 = if len_squared > 0.0 {
+            Vec3::new(-vh.y(), vh.x(), 0.0) / len_squared.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = cross(vh, t1);
+
+        let r = sampler.random().sqrt();
+        let phi = 2.0 * PI * sampler.random();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z());
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+        let h = unit_vector(Vec3::new(
+            self.alpha_x * nh.x(),
+            self.alpha_y * nh.y(),
+            nh.z().max(1e-6),
+        ));
+        2.0 * (wo.x() * h.x() + wo.y() * h.y() + wo.z() * h.z()) * h - wo
+    }
+}
+
+/// Fresnel reflectance of a conductor with complex index of refraction
+/// `eta + i k` per channel, for light arriving `cos_theta` from the normal.
+pub fn fresnel_conductor(cos_theta: f64, eta: Color, k: Color) -> Color {
+    let cos2 = cos_theta.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let reflectance = |eta: f64, k: f64| {
+        let t0 = eta * eta - k * k - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let t2 = 2.0 * cos2.sqrt() * a;
+        let rs = (t1 - t2) / (t1 + t2);
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+        0.5 * (rs + rp)
+    };
+    Color::new(
+        reflectance(eta.x(), k.x()),
+        reflectance(eta.y(), k.y()),
+        reflectance(eta.z(), k.z()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visible_normal_sampling_matches_its_density() {
+        let mut sampler = Sampler::new(9);
+        for (alpha_x, alpha_y) in [(0.2, 0.2), (0.5, 0.5), (0.8, 0.2)] {
+            let distribution = TrowbridgeReitz::new(alpha_x, alpha_y);
+            let wo = unit_vector(Vec3::new(0.4, -0.3, 0.6));
+            let n = 100_000;
+            let mut above = 0;
+            for _ in 0..n {
+                let wi = distribution.sample_reflection(wo, &mut sampler);
+                assert!((wi.length() - 1.0).abs() < 1e-9);
+                if wi.z() > 0.0 {
+                    above += 1;
+                    assert!(distribution.reflection_pdf(wo, wi) > 0.0);
+                }
+            }
+
+            // The density integrates over the hemisphere to the fraction of
+            // reflections that stay outside the surface.
+            let mut integral = 0.0;
+            for _ in 0..n {
+                let z = sampler.random();
+                let phi = 2.0 * PI * sampler.random();
+                let r = (1.0 - z * z).sqrt();
+                let wi = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+                integral += distribution.reflection_pdf(wo, wi) * 2.0 * PI;
+            }
+            let fraction = above as f64 / n as f64;
+            assert!((integral / n as f64 - fraction).abs() < 0.02, "{alpha_x}, {alpha_y}");
+        }
+    }
+
+    #[test]
+    fn conductor_fresnel_matches_known_limits() {
+        let gold_eta = Color::new(0.143, 0.374, 1.442);
+        let gold_k = Color::new(3.983, 2.385, 1.603);
+        let normal = fresnel_conductor(1.0, gold_eta, gold_k);
+        // At normal incidence ((n - 1)^2 + k^2) / ((n + 1)^2 + k^2).
+        let expected = |n: f64, k: f64| ((n - 1.0).powi(2) + k * k) / ((n + 1.0).powi(2) + k * k);
+        for i in 0..3 {
+            assert!((normal[i] - expected(gold_eta[i], gold_k[i])).abs() < 1e-12);
+        }
+        assert!(normal.x() > normal.y() && normal.y() > normal.z());
+        // Every metal becomes a perfect mirror at grazing angles.
+        let grazing = fresnel_conductor(0.0, gold_eta, gold_k);
+        assert!((grazing - Color::new(1.0, 1.0, 1.0)).length() < 1e-12);
+    }
+}
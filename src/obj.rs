@@ -0,0 +1,549 @@
+//! Wavefront OBJ meshes with MTL materials.
+//!
+//! Supports `v`, `vt`, `vn` and `f` records (polygons are fan
+//! triangulated, negative indices count back from the end), `g`/`o`
+//! groups, and `mtllib`/`usemtl`. Every group becomes its own [`Mesh`]
+//! with a BVH over its triangles. Other statements (`s`, `l`, ...) are
+//! ignored.
+//!
+//! MTL materials map onto the built-in types: a non-zero `Ke` gives a
+//! [`DiffuseLight`], `d` below 1 a [`Dielectric`] with index `Ni`, a
+//! specular `Ks` brighter than `Kd` a [`Metal`] whose fuzz comes from the
+//! `Ns` exponent, and anything else a [`Lambertian`] with albedo `Kd`.
+
+use crate::aabb::Aabb;
+use crate::bvh::BvhNode;
+use crate::color::Color;
+use crate::hit::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::ray::Ray;
+use crate::triangle::Triangle;
+use crate::vec3::{Point3, Vec3};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            ObjError::Parse { .. } => None,
+        }
+    }
+}
+
+/// The triangles of one OBJ group, with their own bounding volume hierarchy.
+pub struct Mesh {
+    name: String,
+    triangle_count: usize,
+    bvh: BvhNode,
+}
+
+impl Mesh {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangle_count
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.bvh.hit(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        self.bvh.emitters()
+    }
+}
+
+/// Loads every group in an OBJ file. Faces that come before any `usemtl`
+/// use `default_material`.
+///
+/// Unlike many loaders, which quietly fall back to a default, a `usemtl`
+/// naming a material that no earlier `mtllib` defines is an error: a typo
+/// or a missing MTL file would otherwise go unnoticed until the render.
+pub fn load_obj<P: AsRef<Path>>(
+    path: P,
+    default_material: Arc<dyn Material>,
+) -> Result<Vec<Mesh>, ObjError> {
+    let path = path.as_ref();
+    let src = read(path)?;
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    parse_obj(&src, path, default_material, |name| {
+        let mtl_path = dir.join(name);
+        parse_mtl(&read(&mtl_path)?, &mtl_path)
+    })
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    std::fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Parses OBJ source. `path` is only used in error messages, and
+/// `load_mtl` resolves the libraries named by `mtllib`.
+pub fn parse_obj<F>(
+    src: &str,
+    path: &Path,
+    default_material: Arc<dyn Material>,
+    mut load_mtl: F,
+) -> Result<Vec<Mesh>, ObjError>
+where
+    F: FnMut(&str) -> Result<HashMap<String, Arc<dyn Material>>, ObjError>,
+{
+    let mut positions: Vec<Point3> = Vec::new();
+    let mut texcoords: Vec<(f64, f64)> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    let mut current_material = default_material.clone();
+
+    let mut meshes = Vec::new();
+    let mut group_name = String::from("default");
+    let mut group: Vec<Arc<dyn Hittable>> = Vec::new();
+
+    for (index, raw_line) in src.lines().enumerate() {
+        let line = LineParser {
+            path,
+            number: index + 1,
+        };
+        let content = raw_line.split('#').next().unwrap_or("");
+        let mut tokens = content.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => positions.push(line.vec3(keyword, &args, 3..=4)?),
+            "vn" => normals.push(line.vec3(keyword, &args, 3..=3)?),
+            "vt" => {
+                let values = line.floats(keyword, &args, 1..=3)?;
+                texcoords.push((values[0], values.get(1).copied().unwrap_or(0.0)));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(line.error(format!(
+                        "face needs at least 3 vertices, found {}",
+                        args.len()
+                    )));
+                }
+                let corners = args
+                    .iter()
+                    .map(|arg| line.face_vertex(arg, positions.len(), texcoords.len(), normals.len()))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                for k in 1..corners.len() - 1 {
+                    let [a, b, c] = [corners[0], corners[k], corners[k + 1]];
+                    let mut triangle = Triangle::new(
+                        positions[a.0],
+                        positions[b.0],
+                        positions[c.0],
+                        current_material.clone(),
+                    );
+                    if let (Some(ta), Some(tb), Some(tc)) = (a.1, b.1, c.1) {
+                        triangle = triangle.with_uvs([texcoords[ta], texcoords[tb], texcoords[tc]]);
+                    }
+                    if let (Some(na), Some(nb), Some(nc)) = (a.2, b.2, c.2) {
+                        triangle = triangle.with_normals([normals[na], normals[nb], normals[nc]]);
+                    }
+                    group.push(Arc::new(triangle));
+                }
+            }
+            "g" | "o" => {
+                finish_group(&mut meshes, &group_name, &mut group);
+                group_name = if args.is_empty() {
+                    String::from("default")
+                } else {
+                    args.join(" ")
+                };
+            }
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(line.error("mtllib needs a file name"));
+                }
+                for name in args {
+                    materials.extend(load_mtl(name)?);
+                }
+            }
+            "usemtl" => {
+                let [name] = args.as_slice() else {
+                    return Err(line.error("usemtl needs exactly one material name"));
+                };
+                current_material = match materials.get(*name) {
+                    Some(material) => material.clone(),
+                    None => return Err(line.error(format!("undefined material \"{name}\""))),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    finish_group(&mut meshes, &group_name, &mut group);
+    Ok(meshes)
+}
+
+fn finish_group(meshes: &mut Vec<Mesh>, name: &str, group: &mut Vec<Arc<dyn Hittable>>) {
+    if group.is_empty() {
+        return;
+    }
+    meshes.push(Mesh {
+        name: name.to_string(),
+        triangle_count: group.len(),
+        bvh: BvhNode::new(group),
+    });
+    group.clear();
+}
+
+/// Parses an MTL library into materials keyed by their `newmtl` names.
+pub fn parse_mtl(src: &str, path: &Path) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlProperties)> = None;
+
+    for (index, raw_line) in src.lines().enumerate() {
+        let line = LineParser {
+            path,
+            number: index + 1,
+        };
+        let content = raw_line.split('#').next().unwrap_or("");
+        let mut tokens = content.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err(line.error("newmtl needs a material name"));
+            }
+            if let Some((name, props)) = current.take() {
+                materials.insert(name, props.to_material());
+            }
+            current = Some((args.join(" "), MtlProperties::default()));
+            continue;
+        }
+
+        let props = match (&mut current, keyword) {
+            (Some((_, props)), _) => props,
+            (None, "Kd" | "Ks" | "Ke" | "Ni" | "Ns" | "d" | "Tr") => {
+                return Err(line.error(format!("{keyword} before any newmtl")));
+            }
+            (None, _) => continue,
+        };
+        match keyword {
+            "Kd" => props.kd = line.color(keyword, &args)?,
+            "Ks" => props.ks = line.color(keyword, &args)?,
+            "Ke" => props.ke = line.color(keyword, &args)?,
+            "Ni" => props.ni = line.floats(keyword, &args, 1..=1)?[0],
+            "Ns" => props.ns = line.floats(keyword, &args, 1..=1)?[0],
+            "d" => props.d = line.floats(keyword, &args, 1..=1)?[0],
+            "Tr" => props.d = 1.0 - line.floats(keyword, &args, 1..=1)?[0],
+            _ => {}
+        }
+    }
+
+    if let Some((name, props)) = current {
+        materials.insert(name, props.to_material());
+    }
+    Ok(materials)
+}
+
+struct MtlProperties {
+    kd: Color,
+    ks: Color,
+    ke: Color,
+    ni: f64,
+    ns: f64,
+    d: f64,
+}
+
+impl Default for MtlProperties {
+    fn default() -> Self {
+        MtlProperties {
+            kd: Color::new(0.8, 0.8, 0.8),
+            ks: Color::new(0.0, 0.0, 0.0),
+            ke: Color::new(0.0, 0.0, 0.0),
+            ni: 1.5,
+            ns: 0.0,
+            d: 1.0,
+        }
+    }
+}
+
+impl MtlProperties {
+    fn to_material(&self) -> Arc<dyn Material> {
+        let max = |c: Color| c.x().max(c.y()).max(c.z());
+
+        if max(self.ke) > 0.0 {
+            Arc::new(DiffuseLight::new(self.ke))
+        } else if self.d < 1.0 {
+            Arc::new(Dielectric::new(self.ni))
+        } else if max(self.ks) > max(self.kd) {
+            // Map the Phong exponent onto a roughness-like fuzz: high
+            // exponents are sharp mirrors, zero is fully diffuse.
+            let fuzz = (2.0 / (self.ns.max(0.0) + 2.0)).sqrt();
+            Arc::new(Metal::new(self.ks, fuzz))
+        } else {
+            Arc::new(Lambertian::new(self.kd))
+        }
+    }
+}
+
+/// Helpers that attach the file and line number to every error.
+struct LineParser<'a> {
+    path: &'a Path,
+    number: usize,
+}
+
+impl LineParser<'_> {
+    fn error(&self, message: impl Into<String>) -> ObjError {
+        ObjError::Parse {
+            path: self.path.to_path_buf(),
+            line: self.number,
+            message: message.into(),
+        }
+    }
+
+    fn floats(
+        &self,
+        keyword: &str,
+        args: &[&str],
+        count: std::ops::RangeInclusive<usize>,
+    ) -> Result<Vec<f64>, ObjError> {
+        if !count.contains(&args.len()) {
+            let expected = if count.start() == count.end() {
+                count.start().to_string()
+            } else {
+                format!("{} to {}", count.start(), count.end())
+            };
+            return Err(self.error(format!(
+                "{keyword} expects {expected} numbers, found {}",
+                args.len()
+            )));
+        }
+        args.iter()
+            .map(|arg| match arg.parse::<f64>() {
+                Ok(n) if n.is_finite() => Ok(n),
+                _ => Err(self.error(format!("invalid number '{arg}' in {keyword}"))),
+            })
+            .collect()
+    }
+
+    fn vec3(
+        &self,
+        keyword: &str,
+        args: &[&str],
+        count: std::ops::RangeInclusive<usize>,
+    ) -> Result<Vec3, ObjError> {
+        let v = self.floats(keyword, args, count)?;
+        Ok(Vec3::new(v[0], v[1], v[2]))
+    }
+
+    fn color(&self, keyword: &str, args: &[&str]) -> Result<Color, ObjError> {
+        // A single value is shorthand for a gray.
+        let v = self.floats(keyword, args, 1..=3)?;
+        match v.as_slice() {
+            [g] => Ok(Color::new(*g, *g, *g)),
+            [r, g, b] => Ok(Color::new(*r, *g, *b)),
+            _ => Err(self.error(format!("{keyword} expects 1 or 3 numbers"))),
+        }
+    }
+
+    /// Resolves one `v`, `v/vt`, `v//vn` or `v/vt/vn` face corner into
+    /// zero-based indices.
+    fn face_vertex(
+        &self,
+        arg: &str,
+        positions: usize,
+        texcoords: usize,
+        normals: usize,
+    ) -> Result<(usize, Option<usize>, Option<usize>), ObjError> {
+        let parts: Vec<&str> = arg.split('/').collect();
+        if parts.len() > 3 {
+            return Err(self.error(format!("invalid face vertex '{arg}'")));
+        }
+
+        let position = self.index(arg, parts[0], positions, "vertex")?;
+        let texcoord = match parts.get(1) {
+            Some(s) if !s.is_empty() => Some(self.index(arg, s, texcoords, "texture coordinate")?),
+            _ => None,
+        };
+        let normal = match parts.get(2) {
+            Some(s) if !s.is_empty() => Some(self.index(arg, s, normals, "normal")?),
+            Some(_) => return Err(self.error(format!("invalid face vertex '{arg}'"))),
+            None => None,
+        };
+        Ok((position, texcoord, normal))
+    }
+
+    fn index(&self, arg: &str, s: &str, len: usize, what: &str) -> Result<usize, ObjError> {
+        let i: i64 = s
+            .parse()
+            .map_err(|_| self.error(format!("invalid face vertex '{arg}'")))?;
+        let resolved = match i {
+            1.. => i - 1,
+            ..=-1 => len as i64 + i,
+            0 => return Err(self.error(format!("{what} index 0 in '{arg}' is invalid"))),
+        };
+        if resolved < 0 || resolved >= len as i64 {
+            return Err(self.error(format!(
+                "{what} index {i} in '{arg}' is out of range ({len} defined)"
+            )));
+        }
+        Ok(resolved as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    fn no_mtl(_: &str) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+        panic!("unexpected mtllib")
+    }
+
+    fn error(src: &str) -> String {
+        match parse_obj(src, Path::new("test.obj"), gray(), no_mtl) {
+            Err(err) => err.to_string(),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn groups_become_meshes_and_polygons_are_fan_triangulated() {
+        let src = "\
+# a unit square and a triangle
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+g square
+f 1/1/1 2/2/1 3/3/1 4/4/1
+o tri
+f -4//1 -3//1 -2//1
+";
+        let meshes = parse_obj(src, Path::new("test.obj"), gray(), no_mtl).unwrap();
+        assert_eq!(meshes.len(), 2);
+        assert_eq!((meshes[0].name(), meshes[0].triangle_count()), ("square", 2));
+        assert_eq!((meshes[1].name(), meshes[1].triangle_count()), ("tri", 1));
+
+        let r = Ray::new(Point3::new(0.75, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = meshes[0].hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+        assert!((rec.u - 0.75).abs() < 1e-12 && (rec.v - 0.25).abs() < 1e-12);
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn usemtl_maps_mtl_properties_onto_materials() {
+        let mtl = "\
+newmtl glass
+Kd 1 1 1
+d 0.2
+Ni 1.33
+newmtl lamp
+Ke 4 4 4
+";
+        let materials = parse_mtl(mtl, Path::new("test.mtl")).unwrap();
+        assert_eq!(materials.len(), 2);
+
+        let src = "\
+mtllib test.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 5 0 0
+v 6 0 0
+v 5 1 0
+usemtl lamp
+f 1 2 3
+usemtl glass
+f 4 5 6
+";
+        let meshes = parse_obj(src, Path::new("test.obj"), gray(), |name| {
+            assert_eq!(name, "test.mtl");
+            Ok(materials.clone())
+        })
+        .unwrap();
+
+        let r = Ray::new(Point3::new(0.2, 0.2, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = meshes[0].hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+        assert_eq!(rec.mat.emitted(&r, &rec), Color::new(4.0, 4.0, 4.0));
+
+        // Only the lamp's face is sampled as a light.
+        let lights = meshes[0].emitters().unwrap();
+        assert!(lights.pdf_value(r.origin(), r.direction(), 0.0) > 0.0);
+        assert_eq!(lights.pdf_value(r.origin(), Vec3::new(5.0, 0.0, -1.0), 0.0), 0.0);
+    }
+
+    #[test]
+    fn malformed_lines_report_their_line_number() {
+        assert_eq!(error("v 0 0 0\nv 1 zero 0\n"), "test.obj:2: invalid number 'zero' in v");
+        assert_eq!(error("v 0 0\n"), "test.obj:1: v expects 3 to 4 numbers, found 2");
+        assert_eq!(
+            error("v 0 0 0\nv 1 0 0\nv 0 1 0\n\nf 1 2 4\n"),
+            "test.obj:5: vertex index 4 in '4' is out of range (3 defined)"
+        );
+        assert_eq!(
+            error("v 0 0 0\nv 1 0 0\nf 1 2\n"),
+            "test.obj:3: face needs at least 3 vertices, found 2"
+        );
+        assert_eq!(
+            error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1 2/1 3/1\n"),
+            "test.obj:4: texture coordinate index 1 in '1/1' is out of range (0 defined)"
+        );
+        assert_eq!(
+            error("v 0 0 0\nusemtl missing\n"),
+            "test.obj:2: undefined material \"missing\""
+        );
+        assert_eq!(
+            parse_mtl("Kd 1 1 1\n", Path::new("a.mtl")).err().unwrap().to_string(),
+            "a.mtl:1: Kd before any newmtl"
+        );
+    }
+}
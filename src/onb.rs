@@ -0,0 +1,58 @@
+use crate::vec3::{cross, dot, unit_vector, Vec3};
+
+/// An orthonormal basis whose `w` axis follows a given direction, for
+/// turning directions sampled around the z axis into world space.
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl Onb {
+    pub fn new(n: Vec3) -> Self {
+        let w = unit_vector(n);
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = unit_vector(cross(w, a));
+        let u = cross(w, v);
+        Onb { u, v, w }
+    }
+
+    /// A basis with `w` along `n` and `u` along the part of `tangent`
+    /// perpendicular to it, or any `u` if `tangent` is parallel to `n`.
+    pub fn with_tangent(n: Vec3, tangent: Vec3) -> Self {
+        let w = unit_vector(n);
+        let u = tangent - w * dot(w, tangent);
+        if u.near_zero() {
+            return Onb::new(n);
+        }
+        let u = unit_vector(u);
+        Onb { u, v: cross(w, u), w }
+    }
+
+    pub fn u(&self) -> Vec3 {
+        self.u
+    }
+
+    pub fn v(&self) -> Vec3 {
+        self.v
+    }
+
+    pub fn w(&self) -> Vec3 {
+        self.w
+    }
+
+    /// Maps `local` coordinates along (u, v, w) to a world-space vector.
+    pub fn transform(&self, local: Vec3) -> Vec3 {
+        local.x() * self.u + local.y() * self.v + local.z() * self.w
+    }
+
+    /// The coordinates of world-space `v` along (u, v, w).
+    pub fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3::new(dot(v, self.u), dot(v, self.v), dot(v, self.w))
+    }
+}
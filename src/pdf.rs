@@ -0,0 +1,204 @@
+//! Probability densities over directions, for importance sampling.
+
+use crate::microfacet::TrowbridgeReitz;
+use crate::onb::Onb;
+use crate::phase::{HenyeyGreenstein, PhaseFunction};
+use crate::sampler::Sampler;
+use crate::vec3::{dot, unit_vector, Vec3};
+use std::f64::consts::PI;
+
+/// A distribution of directions that can be both sampled and evaluated, so
+/// a sample drawn from it can be weighted by its density.
+pub trait Pdf: Send + Sync {
+    /// Density, with respect to solid angle, of sampling `direction`.
+    fn value(&self, direction: Vec3) -> f64;
+
+    fn generate(&self, sampler: &mut Sampler) -> Vec3;
+}
+
+/// Uniform over the whole sphere of directions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self, sampler: &mut Sampler) -> Vec3 {
+        let z = 1.0 - 2.0 * sampler.random();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * sampler.random();
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+}
+
+/// Proportional to the cosine of the angle from a normal, over the
+/// hemisphere it points into: exactly the distribution of light a
+/// Lambertian surface reflects.
+#[derive(Debug, Clone, Copy)]
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(normal: Vec3) -> Self {
+        CosinePdf {
+            uvw: Onb::new(normal),
+        }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        let cosine_theta = dot(unit_vector(direction), self.uvw.w());
+        (cosine_theta / PI).max(0.0)
+    }
+
+    fn generate(&self, sampler: &mut Sampler) -> Vec3 {
+        self.uvw.transform(random_cosine_direction(sampler))
+    }
+}
+
+/// Samples a [`HenyeyGreenstein`] phase function for light travelling in
+/// direction `wo`.
+#[derive(Debug, Clone, Copy)]
+pub struct PhasePdf {
+    phase: HenyeyGreenstein,
+    wo: Vec3,
+}
+
+impl PhasePdf {
+    pub fn new(phase: HenyeyGreenstein, wo: Vec3) -> Self {
+        PhasePdf { phase, wo }
+    }
+}
+
+impl Pdf for PhasePdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.phase.p(self.wo, direction)
+    }
+
+    fn generate(&self, sampler: &mut Sampler) -> Vec3 {
+        self.phase.sample(self.wo, sampler)
+    }
+}
+
+/// Reflection off the microfacets of a [`TrowbridgeReitz`] surface that are
+/// visible from `wo`, given in the local coordinates of `frame`.
+#[derive(Debug, Clone, Copy)]
+pub struct MicrofacetPdf {
+    frame: Onb,
+    distribution: TrowbridgeReitz,
+    wo: Vec3,
+}
+
+impl MicrofacetPdf {
+    pub fn new(frame: Onb, distribution: TrowbridgeReitz, wo: Vec3) -> Self {
+        MicrofacetPdf {
+            frame,
+            distribution,
+            wo,
+        }
+    }
+}
+
+impl Pdf for MicrofacetPdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        let wi = self.frame.to_local(unit_vector(direction));
+        self.distribution.reflection_pdf(self.wo, wi)
+    }
+
+    fn generate(&self, sampler: &mut Sampler) -> Vec3 {
+        self.frame.transform(self.distribution.sample_reflection(self.wo, sampler))
+    }
+}
+
+/// Picks one of two densities with probability `weight` and `1 - weight`,
+/// so directions either one favours are sampled well.
+pub struct MixturePdf<'a> {
+    p: [&'a dyn Pdf; 2],
+    weight: f64,
+}
+
+impl<'a> MixturePdf<'a> {
+    /// An even mix of `p0` and `p1`.
+    pub fn new(p0: &'a dyn Pdf, p1: &'a dyn Pdf) -> Self {
+        MixturePdf::with_weight(p0, p1, 0.5)
+    }
+
+    pub fn with_weight(p0: &'a dyn Pdf, p1: &'a dyn Pdf, weight: f64) -> Self {
+        MixturePdf {
+            p: [p0, p1],
+            weight: weight.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.weight * self.p[0].value(direction) + (1.0 - self.weight) * self.p[1].value(direction)
+    }
+
+    fn generate(&self, sampler: &mut Sampler) -> Vec3 {
+        if sampler.random() < self.weight {
+            self.p[0].generate(sampler)
+        } else {
+            self.p[1].generate(sampler)
+        }
+    }
+}
+
+/// A direction about +z with density `cos(theta) / pi`.
+pub fn random_cosine_direction(sampler: &mut Sampler) -> Vec3 {
+    let r1 = sampler.random();
+    let r2 = sampler.random();
+
+    let phi = 2.0 * PI * r1;
+    let x = phi.cos() * r2.sqrt();
+    let y = phi.sin() * r2.sqrt();
+    let z = (1.0 - r2).sqrt();
+
+    Vec3::new(x, y, z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Estimates the integral of `f` over the sphere by importance sampling
+    /// with `pdf`.
+    fn integrate(pdf: &dyn Pdf, f: impl Fn(Vec3) -> f64) -> f64 {
+        let mut sampler = Sampler::new(5);
+        let n = 100_000;
+        (0..n)
+            .map(|_| {
+                let d = pdf.generate(&mut sampler);
+                f(d) / pdf.value(d)
+            })
+            .sum::<f64>()
+            / n as f64
+    }
+
+    #[test]
+    fn sampled_densities_match_their_values() {
+        // Every density averages 1 / 4 pi over uniformly drawn directions,
+        // and f = cos^2 over the upper hemisphere integrates to 2 pi / 3
+        // whichever pdf samples it.
+        let normal = unit_vector(Vec3::new(1.0, 1.0, 0.0));
+        let cosine = CosinePdf::new(normal);
+        let phase = PhasePdf::new(HenyeyGreenstein::new(0.7), normal);
+        let pdfs: [&dyn Pdf; 4] = [&SpherePdf, &cosine, &phase, &MixturePdf::new(&cosine, &SpherePdf)];
+        for pdf in pdfs {
+            let total = integrate(&SpherePdf, |d| pdf.value(d));
+            assert!((total - 1.0).abs() < 0.02, "{total}");
+        }
+
+        let cos_squared = |d: Vec3| dot(unit_vector(d), normal).max(0.0).powi(2);
+        let mixture = MixturePdf::with_weight(&cosine, &SpherePdf, 0.3);
+        for pdf in [&SpherePdf as &dyn Pdf, &cosine, &phase, &mixture] {
+            let estimate = integrate(pdf, cos_squared);
+            assert!((estimate - 2.0 * PI / 3.0).abs() < 0.03, "{estimate}");
+        }
+    }
+}
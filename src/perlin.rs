@@ -0,0 +1,118 @@
+use crate::sampler::Sampler;
+use crate::vec3::{dot, unit_vector, Point3, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+/// Gradient noise over 3D space with a fixed, seeded lattice.
+pub struct Perlin {
+    randvec: [Vec3; POINT_COUNT],
+    perm_x: [usize; POINT_COUNT],
+    perm_y: [usize; POINT_COUNT],
+    perm_z: [usize; POINT_COUNT],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut sampler = Sampler::new(seed);
+        let randvec =
+            std::array::from_fn(|_| unit_vector(Vec3::random_from_range(&mut sampler, -1.0, 1.0)));
+        Perlin {
+            randvec,
+            perm_x: generate_perm(&mut sampler),
+            perm_y: generate_perm(&mut sampler),
+            perm_z: generate_perm(&mut sampler),
+        }
+    }
+
+    /// Smoothly varying noise in roughly [-1, 1].
+    pub fn noise(&self, p: &Point3) -> f64 {
+        let u = p.x() - p.x().floor();
+        let v = p.y() - p.y().floor();
+        let w = p.z() - p.z().floor();
+
+        let i = p.x().floor() as i64;
+        let j = p.y().floor() as i64;
+        let k = p.z().floor() as i64;
+
+        let mut c = [[[Vec3::new(0.0, 0.0, 0.0); 2]; 2]; 2];
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, corner) in row.iter_mut().enumerate() {
+                    let index = self.perm_x[((i + di as i64) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i64) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i64) & 255) as usize];
+                    *corner = self.randvec[index];
+                }
+            }
+        }
+
+        perlin_interp(&c, u, v, w)
+    }
+
+    /// Sum of `depth` octaves of absolute noise, each at twice the frequency
+    /// and half the weight of the last.
+    pub fn turb(&self, p: &Point3, depth: usize) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = *p;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(&temp_p);
+            weight *= 0.5;
+            temp_p *= 2.0;
+        }
+
+        accum.abs()
+    }
+}
+
+fn generate_perm(sampler: &mut Sampler) -> [usize; POINT_COUNT] {
+    let mut p: [usize; POINT_COUNT] = std::array::from_fn(|i| i);
+    for i in (1..POINT_COUNT).rev() {
+        let target = (sampler.random() * (i + 1) as f64) as usize;
+        p.swap(i, target);
+    }
+    p
+}
+
+fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+    // Hermite smoothing hides the lattice's grid artifacts.
+    let uu = u * u * (3.0 - 2.0 * u);
+    let vv = v * v * (3.0 - 2.0 * v);
+    let ww = w * w * (3.0 - 2.0 * w);
+
+    let mut accum = 0.0;
+    for (i, plane) in c.iter().enumerate() {
+        for (j, row) in plane.iter().enumerate() {
+            for (k, corner) in row.iter().enumerate() {
+                let (fi, fj, fk) = (i as f64, j as f64, k as f64);
+                let weight_v = Vec3::new(u - fi, v - fj, w - fk);
+                accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                    * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                    * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                    * dot(*corner, weight_v);
+            }
+        }
+    }
+    accum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_is_seeded_and_vanishes_on_the_lattice() {
+        let a = Perlin::new(1);
+        let p = Point3::new(1.3, -2.7, 0.45);
+        assert_eq!(a.noise(&p), Perlin::new(1).noise(&p));
+        assert_ne!(a.noise(&p), Perlin::new(2).noise(&p));
+        assert_eq!(a.noise(&Point3::new(3.0, -1.0, 7.0)), 0.0);
+
+        for i in 0..200 {
+            let q = Point3::new(i as f64 * 0.37, i as f64 * -0.11, i as f64 * 0.73);
+            assert!(a.noise(&q).abs() <= 1.0);
+            assert!(a.turb(&q, 7) >= 0.0);
+        }
+    }
+}
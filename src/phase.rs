@@ -0,0 +1,118 @@
+use crate::onb::Onb;
+use crate::sampler::Sampler;
+use crate::vec3::{dot, unit_vector, Vec3};
+use std::f64::consts::PI;
+
+/// How a participating medium redistributes light that scatters inside it.
+///
+/// Directions follow the light: `wo` is the direction the incoming ray was
+/// travelling and `wi` the direction it leaves in, so forward scattering
+/// keeps `wi` close to `wo`.
+pub trait PhaseFunction: Send + Sync {
+    /// Probability density over the sphere of scattering from `wo` into `wi`.
+    fn p(&self, wo: Vec3, wi: Vec3) -> f64;
+
+    /// Samples `wi` exactly in proportion to [`PhaseFunction::p`].
+    fn sample(&self, wo: Vec3, sampler: &mut Sampler) -> Vec3;
+}
+
+/// Scatters equally in every direction.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IsotropicPhase;
+
+impl PhaseFunction for IsotropicPhase {
+    fn p(&self, _wo: Vec3, _wi: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn sample(&self, _wo: Vec3, sampler: &mut Sampler) -> Vec3 {
+        let z = 1.0 - 2.0 * sampler.random();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * sampler.random();
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+}
+
+/// The Henyey-Greenstein phase function. The asymmetry `g` in (-1, 1) is the
+/// mean cosine of the scattering angle: positive values scatter forward, as
+/// haze and clouds do, and negative values scatter back.
+#[derive(Debug, Clone, Copy)]
+pub struct HenyeyGreenstein {
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f64) -> Self {
+        HenyeyGreenstein {
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+
+    pub fn g(&self) -> f64 {
+        self.g
+    }
+}
+
+impl PhaseFunction for HenyeyGreenstein {
+    fn p(&self, wo: Vec3, wi: Vec3) -> f64 {
+        let cos_theta = dot(unit_vector(wo), unit_vector(wi));
+        let g = self.g;
+        let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+    }
+
+    fn sample(&self, wo: Vec3, sampler: &mut Sampler) -> Vec3 {
+        let g = self.g;
+        let u = sampler.random();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * sampler.random();
+
+        Onb::new(wo).transform(Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn henyey_greenstein_sampling_has_mean_cosine_g() {
+        let wo = unit_vector(Vec3::new(1.0, 2.0, -0.5));
+        let mut sampler = Sampler::new(4);
+        for g in [-0.6, 0.0, 0.3, 0.85] {
+            let phase = HenyeyGreenstein::new(g);
+            let n = 50_000;
+            let mean = (0..n)
+                .map(|_| dot(wo, phase.sample(wo, &mut sampler)))
+                .sum::<f64>()
+                / n as f64;
+            assert!((mean - g).abs() < 0.01, "g = {g}, mean cosine {mean}");
+        }
+    }
+
+    #[test]
+    fn phase_functions_integrate_to_one() {
+        // Monte Carlo over uniformly sampled directions, whose pdf is 1/(4 pi).
+        let wo = Vec3::new(0.0, 0.0, 1.0);
+        let mut sampler = Sampler::new(8);
+        let phases: [&dyn PhaseFunction; 2] = [&IsotropicPhase, &HenyeyGreenstein::new(0.5)];
+        for phase in phases {
+            let n = 200_000;
+            let integral = (0..n)
+                .map(|_| phase.p(wo, IsotropicPhase.sample(wo, &mut sampler)) * 4.0 * PI)
+                .sum::<f64>()
+                / n as f64;
+            assert!((integral - 1.0).abs() < 0.02, "{integral}");
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable, HittableList},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    sampler::Sampler,
+    vec3::{cross, dot, unit_vector, Point3, Vec3},
+};
+use std::sync::Arc;
+
+/// A parallelogram with corner `q` and edges `u` and `v`. Hits report
+/// `(u, v)` texture coordinates in `[0, 1]` along those edges, and the
+/// surface is visible from both sides.
+#[derive(Clone)]
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        let n = cross(u, v);
+        let normal = unit_vector(n);
+        let d = dot(normal, q);
+        let w = n / dot(n, n);
+
+        let bbox_diagonal1 = Aabb::from_points(q, q + u + v);
+        let bbox_diagonal2 = Aabb::from_points(q + u, q + v);
+        let bbox = Aabb::from_boxes(&bbox_diagonal1, &bbox_diagonal2);
+
+        Quad {
+            q,
+            u,
+            v,
+            w,
+            mat,
+            bbox,
+            normal,
+            d,
+            area: n.length(),
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let denom = dot(self.normal, r.direction());
+
+        // No hit if the ray is parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.d - dot(self.normal, r.origin())) / denom;
+        if !ray_t.contains(t) {
+            return None;
+        }
+
+        // Express the hit point in the plane's (u, v) basis and check that
+        // it falls inside the parallelogram.
+        let intersection = r.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = dot(self.w, cross(planar_hitpt_vector, self.v));
+        let beta = dot(self.w, cross(self.u, planar_hitpt_vector));
+
+        let unit_interval = Interval::new(0.0, 1.0);
+        if !unit_interval.contains(alpha) || !unit_interval.contains(beta) {
+            return None;
+        }
+
+        let mut rec = HitRecord {
+            t,
+            p: intersection,
+            u: alpha,
+            v: beta,
+            tangent: self.u,
+            normal: Vec3::new(0.0, 0.0, 0.0),
+            front_face: false,
+            mat: self.mat.clone(),
+        };
+        rec.set_face_normal(r, self.normal);
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        let r = Ray::with_time(origin, direction, time);
+        let Some(rec) = self.hit(&r, Interval::new(0.001, f64::INFINITY)) else {
+            return 0.0;
+        };
+
+        // Points are picked uniformly by area; the squared distance over the
+        // foreshortened area converts that density to solid angle.
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = dot(direction, rec.normal).abs() / direction.length();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Point3, _time: f64, sampler: &mut Sampler) -> Vec3 {
+        let p = self.q + (sampler.random() * self.u) + (sampler.random() * self.v);
+        p - origin
+    }
+
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        self.mat.is_emissive().then(|| Arc::new(self.clone()) as Arc<dyn Hittable>)
+    }
+}
+
+/// The six faces of the axis-aligned box with opposite corners `a` and `b`.
+pub fn make_box(a: Point3, b: Point3, mat: Arc<dyn Material>) -> HittableList {
+    let mut sides = HittableList::new();
+
+    let min = Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+    let max = Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+
+    let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+    let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+    let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+
+    let faces = [
+        (Point3::new(min.x(), min.y(), max.z()), dx, dy), // front
+        (Point3::new(max.x(), min.y(), max.z()), -dz, dy), // right
+        (Point3::new(max.x(), min.y(), min.z()), -dx, dy), // back
+        (Point3::new(min.x(), min.y(), min.z()), dz, dy), // left
+        (Point3::new(min.x(), max.y(), max.z()), dx, -dz), // top
+        (Point3::new(min.x(), min.y(), min.z()), dx, dz), // bottom
+    ];
+    for (q, u, v) in faces {
+        sides.add(Arc::new(Quad::new(q, u, v, mat.clone())));
+    }
+
+    sides
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+
+    fn matte() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    #[test]
+    fn reports_uvs_and_faces_the_ray() {
+        let quad = Quad::new(
+            Point3::new(-1.0, -1.0, 0.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            matte(),
+        );
+
+        let front = Ray::new(Point3::new(0.0, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = quad.hit(&front, Interval::new(0.001, f64::INFINITY)).unwrap();
+        assert_eq!(rec.t, 5.0);
+        assert_eq!((rec.u, rec.v), (0.25, 0.75));
+        assert!(rec.front_face);
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+
+        let back = Ray::new(Point3::new(0.0, 0.5, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        let rec = quad.hit(&back, Interval::new(0.001, f64::INFINITY)).unwrap();
+        assert!(!rec.front_face);
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, -1.0));
+
+        let outside = Ray::new(Point3::new(3.5, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(quad.hit(&outside, Interval::new(0.001, f64::INFINITY)).is_none());
+    }
+
+    #[test]
+    fn axis_aligned_quad_has_a_padded_bounding_box() {
+        let quad = Quad::new(
+            Point3::new(0.0, 0.0, 2.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            matte(),
+        );
+        let bbox = quad.bounding_box();
+        assert!(bbox.z.size() > 0.0);
+        assert!(bbox.z.contains(2.0));
+        assert_eq!((bbox.x.min, bbox.x.max), (0.0, 1.0));
+    }
+
+    #[test]
+    fn box_is_closed() {
+        let sides = make_box(Point3::new(1.0, 1.0, 1.0), Point3::new(-1.0, -1.0, -1.0), matte());
+        assert_eq!(sides.objects().len(), 6);
+
+        // Rays from the center along each axis must all leave through a face.
+        let center = Point3::new(0.0, 0.0, 0.0);
+        for axis in 0..3 {
+            for sign in [-1.0, 1.0] {
+                let mut dir = [0.0; 3];
+                dir[axis] = sign;
+                let r = Ray::new(center, Vec3::new(dir[0], dir[1], dir[2]));
+                let rec = sides.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+                assert_eq!(rec.t, 1.0);
+                assert!(!rec.front_face);
+            }
+        }
+    }
+
+    #[test]
+    fn light_sampling_density_integrates_to_the_solid_angle() {
+        // A 2x2 square one unit above the origin subtends 2π/3 steradians.
+        let quad = Quad::new(
+            Point3::new(-1.0, 1.0, -1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            matte(),
+        );
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let mut sampler = crate::sampler::Sampler::new(11);
+        let n = 100_000;
+        let mut solid_angle = 0.0;
+        for _ in 0..n {
+            let pdf = quad.pdf_value(origin, quad.random(origin, 0.0, &mut sampler), 0.0);
+            assert!(pdf > 0.0);
+            solid_angle += 1.0 / pdf;
+        }
+        solid_angle /= n as f64;
+        assert!((solid_angle - 2.0 * std::f64::consts::PI / 3.0).abs() < 0.01, "{solid_angle}");
+        assert_eq!(quad.pdf_value(origin, Vec3::new(0.0, -1.0, 0.0), 0.0), 0.0);
+    }
+}
@@ -4,11 +4,18 @@ use crate::vec3::{Point3, Vec3};
 pub struct Ray {
     orig: Point3,
     dir: Vec3,
+    tm: f64,
 }
 
 impl Ray {
     pub fn new(orig: Point3, dir: Vec3) -> Self {
-        Ray { orig, dir }
+        Ray::with_time(orig, dir, 0.0)
+    }
+
+    /// A ray sent at time `tm`; moving objects are hit where they are at
+    /// that instant.
+    pub fn with_time(orig: Point3, dir: Vec3, tm: f64) -> Self {
+        Ray { orig, dir, tm }
     }
 
     pub fn origin(&self) -> Point3 {
@@ -19,6 +26,10 @@ impl Ray {
         self.dir
     }
 
+    pub fn time(&self) -> f64 {
+        self.tm
+    }
+
     pub fn at(&self, t: f64) -> Point3 {
         self.orig + self.dir * t
     }
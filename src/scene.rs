@@ -0,0 +1,1068 @@
+//! Declarative JSON scene descriptions.
+//!
+//! A scene file is a single object with an optional `camera`, a map of named
+//! `materials` and a list of `objects`:
+//!
+//! ```json
+//! {
+//!   "camera": { "image_width": 400, "lookfrom": [13, 2, 3], "vfov": 20 },
+//!   "materials": {
+//!     "ground": { "type": "lambertian", "albedo": [0.5, 0.5, 0.5] }
+//!   },
+//!   "objects": [
+//!     { "type": "sphere", "center": [0, -1000, 0], "radius": 1000, "material": "ground" },
+//!     { "type": "sphere", "center": [0, 1, 0], "radius": 1,
+//!       "material": { "type": "dielectric", "refraction_index": 1.5 } }
+//!   ]
+//! }
+//! ```
+//!
+//! Objects are `sphere`s (`center`, `radius`, and for motion blur an
+//! optional `center1` reached as the camera's shutter closes), `quad`s
+//! (corner `q` and edge vectors `u` and `v`), `triangle`s (three
+//! `vertices`, with optional per-vertex `normals` and `uvs`) and
+//! axis-aligned `box`es (opposite corners `a` and `b`). A `mesh` loads every
+//! group of a Wavefront OBJ `path`, resolved relative to the scene file; its
+//! `material` is optional and only covers faces before the first `usemtl`,
+//! which must name a material from the OBJ's MTL files. Objects refer to
+//! materials either by name or with an inline definition.
+//! An optional top-level `background` is `"sky"` (the default gradient), a
+//! color, or an `{"type": "environment", "path": ...}` map: an
+//! equirectangular Radiance `.hdr` or PPM image, importance sampled as a
+//! light, with an optional `rotation` in degrees about +y and an
+//! `intensity` scale. A `{"type": "daylight", "sun_elevation": ...}`
+//! background is an analytic clear sky with a sun disk `sun_elevation`
+//! degrees above the horizon and `sun_azimuth` degrees round from -z toward
+//! +x, with an optional `turbidity` from 2 (clear) to 10 (hazy, the default
+//! is 3) and an `intensity` scale. A `[0, 0, 0]` background leaves only emissive
+//! materials (`"diffuse_light"` with an `emit` color) to light the scene.
+//! Every emissive surface among the objects is collected into
+//! [`Scene::lights`] and sampled directly while rendering: spheres, quads,
+//! triangles and boxes made of an emissive material, mesh faces whose MTL
+//! material has a `Ke`, and any of these placed by an `instance`.
+//!
+//! A top-level `lights` list adds punctual lights, which have no geometry:
+//! a `point` light at a `position` with a radiant `intensity` in W/sr, a
+//! `spot` light that also has a `direction`, a `cone_angle` in degrees and
+//! an optional `falloff_start` angle inside which it is at full intensity,
+//! and a `directional` light shining along a `direction` with an
+//! `irradiance` in W/m².
+//!
+//! A top-level `shapes` map names objects that are built once and placed any
+//! number of times by `instance` objects. An instance's `shape` is a shape
+//! name or an inline object, and its `transform` is a list of steps applied
+//! in order: `{"scale": s}` (a number or one factor per axis),
+//! `{"rotate_x": degrees}` (likewise `rotate_y` and `rotate_z`),
+//! `{"translate": [x, y, z]}`, or a row-major 4x4 affine `{"matrix": [...]}`.
+//!
+//! A `constant_medium` fills a convex `boundary` (a shape name or inline
+//! object) with fog of the given `density` that scatters with an `albedo`,
+//! isotropically or, given an `anisotropy` between -1 and 1, forward or
+//! back. A `heterogeneous_medium` takes the same keys, but its `density` is
+//! a field: a voxel `grid` read from a `path` (which also provides the
+//! default boundary), or `noise` with a `max_density`, `scale` and `seed`.
+//!
+//! A `conductor` is a rough metal with measured optical constants, either
+//! those of a `metal` (`"gold"`, `"copper"` or `"aluminium"`) or a complex
+//! index of refraction given as `eta` and `k` colors. Its `roughness` runs
+//! from 0 (the default, a mirror) to 1, and `roughness_u` and `roughness_v`
+//! override it along the direction the surface's `u` texture coordinate
+//! runs and across it, for brushed metal.
+//!
+//! The `albedo` of `lambertian` and `metal` materials is either a color or a
+//! texture: `{"type": "solid", "color": [...]}`, a 3D `checker` with a cell
+//! `scale` and `even`/`odd` albedos, an `image` loaded from a PPM `path`, or
+//! Perlin `noise` with a `scale`, a `seed` and a `style` of `"smooth"`,
+//! `"turbulence"` or `"marble"` (the default).
+
+mod json;
+
+use crate::aabb::Aabb;
+use crate::background::Background;
+use crate::bvh::BvhNode;
+use crate::camera::CameraBuilder;
+use crate::color::Color;
+use crate::environment::EnvironmentMap;
+use crate::hit::{Hittable, HittableList};
+use crate::interval::Interval;
+use crate::light::{DirectionalLight, Light, LightList, PointLight, SpotLight};
+use crate::material::{
+    Anisotropic, ComplexIor, Conductor, Dielectric, DiffuseLight, Isotropic, Lambertian, Material,
+    Metal,
+};
+use crate::matrix::Mat4;
+use crate::medium::{ConstantMedium, DensityField, HeterogeneousMedium, NoiseDensity, VoxelGrid};
+use crate::obj::load_obj;
+use crate::quad::{make_box, Quad};
+use crate::sky::Daylight;
+use crate::sphere::Sphere;
+use crate::texture::{CheckerTexture, ImageTexture, NoiseStyle, NoiseTexture, SolidColor, Texture};
+use crate::transform::Transform;
+use crate::triangle::Triangle;
+use crate::vec3::{Point3, Vec3};
+use json::{Node, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{err}"),
+            SceneError::Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "line {line}, column {column}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io(err) => Some(err),
+            SceneError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+pub struct Scene {
+    pub world: HittableList,
+    /// The punctual lights, and the emissive surfaces among the objects, for
+    /// [`Camera::render_with_lights`](crate::camera::Camera::render_with_lights).
+    pub lights: LightList,
+    pub camera: CameraBuilder,
+}
+
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let src = std::fs::read_to_string(path)?;
+    parse_scene_in(&src, path.parent().unwrap_or(Path::new("")))
+}
+
+/// Parses a scene whose relative mesh paths resolve against the working
+/// directory.
+pub fn parse_scene(src: &str) -> Result<Scene, SceneError> {
+    parse_scene_in(src, Path::new(""))
+}
+
+fn parse_scene_in(src: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    let root = json::parse(src)?;
+    let mut fields = Fields::new(&root)?;
+
+    let mut camera = match fields.get("camera") {
+        Some(node) => parse_camera(node)?,
+        None => CameraBuilder::default(),
+    };
+    if let Some(node) = fields.get("background") {
+        camera = camera.background(parse_background(node, base_dir)?);
+    }
+
+    let mut materials = HashMap::new();
+    if let Some(node) = fields.get("materials") {
+        for (name, def) in as_object(node)? {
+            materials.insert(name.as_str(), parse_material(def, base_dir)?);
+        }
+    }
+
+    // Moving spheres travel while the shutter is open.
+    let shutter = camera.shutter();
+    let mut shapes = HashMap::new();
+    if let Some(node) = fields.get("shapes") {
+        for (name, def) in as_object(node)? {
+            let shape = parse_object(def, &materials, &shapes, base_dir, shutter)?;
+            shapes.insert(name.as_str(), shape);
+        }
+    }
+
+    let mut world = HittableList::new();
+    let mut lights = LightList::new();
+    if let Some(node) = fields.get("objects") {
+        for object in as_array(node)? {
+            let object = parse_object(object, &materials, &shapes, base_dir, shutter)?;
+            if let Some(emitters) = object.emitters() {
+                lights.add_area(emitters);
+            }
+            world.add(object);
+        }
+    }
+    if let Some(node) = fields.get("lights") {
+        for light in as_array(node)? {
+            lights.add(parse_light(light)?);
+        }
+    }
+
+    fields.finish()?;
+    Ok(Scene {
+        world,
+        lights,
+        camera,
+    })
+}
+
+fn parse_camera(node: &Node) -> Result<CameraBuilder, SceneError> {
+    let mut fields = Fields::new(node)?;
+    let mut camera = CameraBuilder::new();
+
+    if let Some(v) = fields.get("aspect_ratio") {
+        camera = camera.aspect_ratio(as_f64(v)?);
+    }
+    if let Some(v) = fields.get("image_width") {
+        camera = camera.image_width(as_u64(v)?);
+    }
+    if let Some(v) = fields.get("image_height") {
+        camera = camera.image_height(as_u64(v)?);
+    }
+    if let Some(v) = fields.get("samples_per_pixel") {
+        camera = camera.samples_per_pixel(as_u64(v)?);
+    }
+    if let Some(v) = fields.get("max_depth") {
+        camera = camera.max_depth(as_u64(v)?);
+    }
+    if let Some(v) = fields.get("min_depth") {
+        camera = camera.min_depth(as_u64(v)?);
+    }
+    if let Some(v) = fields.get("vfov") {
+        camera = camera.vfov(as_f64(v)?);
+    }
+    if let Some(v) = fields.get("lookfrom") {
+        camera = camera.lookfrom(as_vec3(v)?);
+    }
+    if let Some(v) = fields.get("lookat") {
+        camera = camera.lookat(as_vec3(v)?);
+    }
+    if let Some(v) = fields.get("vup") {
+        camera = camera.vup(as_vec3(v)?);
+    }
+    if let Some(v) = fields.get("defocus_angle") {
+        camera = camera.defocus_angle(as_f64(v)?);
+    }
+    if let Some(v) = fields.get("focus_dist") {
+        camera = camera.focus_dist(as_f64(v)?);
+    }
+    if let Some(v) = fields.get("shutter_open") {
+        camera = camera.shutter_open(as_f64(v)?);
+    }
+    if let Some(v) = fields.get("shutter_close") {
+        camera = camera.shutter_close(as_f64(v)?);
+    }
+    fields.finish()?;
+
+    // Catch bad settings here, where the error can point into the file.
+    camera.validate().map_err(|err| node.error(err.to_string()))?;
+    Ok(camera)
+}
+
+fn parse_background(node: &Node, base_dir: &Path) -> Result<Background, SceneError> {
+    match &node.value {
+        Value::String(name) if name == "sky" => Ok(Background::Sky),
+        Value::Array(_) => Ok(Background::Solid(as_vec3(node)?)),
+        Value::Object(_) => {
+            let mut fields = Fields::new(node)?;
+            let kind = fields.require("type")?;
+            let background = match as_str(kind)? {
+                "environment" => {
+                    let path_node = fields.require("path")?;
+                    let path = base_dir.join(as_str(path_node)?);
+                    let map = EnvironmentMap::load(&path)
+                        .map_err(|err| path_node.error(format!("{}: {err}", path.display())))?
+                        .with_rotation(fields.f64_or("rotation", 0.0)?)
+                        .with_intensity(fields.f64_or("intensity", 1.0)?);
+                    Background::Environment(Arc::new(map))
+                }
+                "daylight" => {
+                    let elevation_node = fields.require("sun_elevation")?;
+                    let elevation = as_f64(elevation_node)?;
+                    if !(0.0..=90.0).contains(&elevation) {
+                        return Err(
+                            elevation_node.error("sun elevation must be between 0 and 90 degrees")
+                        );
+                    }
+                    let azimuth = fields.f64_or("sun_azimuth", 0.0)?;
+                    let turbidity = match fields.get("turbidity") {
+                        Some(node) => match as_f64(node)? {
+                            t if (2.0..=10.0).contains(&t) => t,
+                            _ => return Err(node.error("turbidity must be between 2 and 10")),
+                        },
+                        None => 3.0,
+                    };
+                    let sky = Daylight::new(elevation, azimuth, turbidity)
+                        .with_intensity(fields.f64_or("intensity", 1.0)?);
+                    Background::Daylight(Arc::new(sky))
+                }
+                other => return Err(kind.error(format!("unknown background type \"{other}\""))),
+            };
+            fields.finish()?;
+            Ok(background)
+        }
+        _ => Err(node.error("expected \"sky\", a color or an object for the background")),
+    }
+}
+
+fn parse_material(node: &Node, base_dir: &Path) -> Result<Arc<dyn Material>, SceneError> {
+    let mut fields = Fields::new(node)?;
+    let kind = fields.require("type")?;
+    let material: Arc<dyn Material> = match as_str(kind)? {
+        "lambertian" => {
+            let albedo = texture_ref(fields.require("albedo")?, base_dir)?;
+            Arc::new(Lambertian::from_texture(albedo))
+        }
+        "metal" => {
+            let albedo = texture_ref(fields.require("albedo")?, base_dir)?;
+            let fuzz = fields.f64_or("fuzz", 0.0)?;
+            Arc::new(Metal::from_texture(albedo, fuzz))
+        }
+        "conductor" => {
+            let ior = match fields.get("metal") {
+                Some(node) => match as_str(node)? {
+                    "gold" => ComplexIor::GOLD,
+                    "copper" => ComplexIor::COPPER,
+                    "aluminium" => ComplexIor::ALUMINIUM,
+                    other => return Err(node.error(format!("unknown metal \"{other}\""))),
+                },
+                None => ComplexIor {
+                    eta: fields.color("eta")?,
+                    k: fields.color("k")?,
+                },
+            };
+            let roughness = parse_roughness(&mut fields, "roughness", 0.0)?;
+            let roughness_u = parse_roughness(&mut fields, "roughness_u", roughness)?;
+            let roughness_v = parse_roughness(&mut fields, "roughness_v", roughness)?;
+            Arc::new(Conductor::anisotropic(ior, roughness_u, roughness_v))
+        }
+        "dielectric" => Arc::new(Dielectric::new(fields.f64("refraction_index")?)),
+        "diffuse_light" => Arc::new(DiffuseLight::new(fields.color("emit")?)),
+        other => return Err(kind.error(format!("unknown material type \"{other}\""))),
+    };
+    fields.finish()?;
+    Ok(material)
+}
+
+fn parse_roughness(fields: &mut Fields, key: &str, default: f64) -> Result<f64, SceneError> {
+    let Some(node) = fields.get(key) else {
+        return Ok(default);
+    };
+    match as_f64(node)? {
+        r if (0.0..=1.0).contains(&r) => Ok(r),
+        _ => Err(node.error("roughness must be between 0 and 1")),
+    }
+}
+
+/// Builds one object.
+fn parse_object(
+    node: &Node,
+    materials: &HashMap<&str, Arc<dyn Material>>,
+    shapes: &HashMap<&str, Arc<dyn Hittable>>,
+    base_dir: &Path,
+    shutter: Interval,
+) -> Result<Arc<dyn Hittable>, SceneError> {
+    let mut fields = Fields::new(node)?;
+    let kind = fields.require("type")?;
+    let object: Arc<dyn Hittable> = match as_str(kind)? {
+        "sphere" => {
+            let center = fields.vec3("center")?;
+            let center1 = match fields.get("center1") {
+                Some(node) => as_vec3(node)?,
+                None => center,
+            };
+            let radius = fields.f64("radius")?;
+            let mat = material_ref(fields.require("material")?, materials, base_dir)?;
+            Arc::new(Sphere::moving(center, center1, shutter, radius, mat))
+        }
+        "quad" => {
+            let q = fields.vec3("q")?;
+            let u = fields.vec3("u")?;
+            let v = fields.vec3("v")?;
+            let mat = material_ref(fields.require("material")?, materials, base_dir)?;
+            Arc::new(Quad::new(q, u, v, mat))
+        }
+        "triangle" => {
+            let [p0, p1, p2] = vec3_triple(fields.require("vertices")?)?;
+            let mat = material_ref(fields.require("material")?, materials, base_dir)?;
+            let mut triangle = Triangle::new(p0, p1, p2, mat);
+            if let Some(node) = fields.get("normals") {
+                triangle = triangle.with_normals(vec3_triple(node)?);
+            }
+            if let Some(node) = fields.get("uvs") {
+                let [uv0, uv1, uv2] = triple(node)?;
+                triangle = triangle.with_uvs([as_uv(uv0)?, as_uv(uv1)?, as_uv(uv2)?]);
+            }
+            Arc::new(triangle)
+        }
+        "box" => {
+            let a = fields.vec3("a")?;
+            let b = fields.vec3("b")?;
+            let mat = material_ref(fields.require("material")?, materials, base_dir)?;
+            Arc::new(make_box(a, b, mat))
+        }
+        "mesh" => {
+            let path_node = fields.require("path")?;
+            let path = base_dir.join(as_str(path_node)?);
+            let mat = match fields.get("material") {
+                Some(node) => material_ref(node, materials, base_dir)?,
+                None => Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))),
+            };
+            let mut meshes: Vec<Arc<dyn Hittable>> = load_obj(&path, mat)
+                .map_err(|err| path_node.error(err.to_string()))?
+                .into_iter()
+                .map(|mesh| Arc::new(mesh) as Arc<dyn Hittable>)
+                .collect();
+            Arc::new(BvhNode::new(&mut meshes))
+        }
+        "instance" => {
+            let transform_node = fields.require("transform")?;
+            let matrix = parse_transform(transform_node)?;
+            let shape_node = fields.require("shape")?;
+            let shape = shape_ref(shape_node, materials, shapes, base_dir, shutter)?;
+            let instance = Transform::new(shape, matrix).ok_or_else(|| {
+                transform_node.error("transform must be affine and invertible")
+            })?;
+            Arc::new(instance)
+        }
+        "constant_medium" => {
+            let boundary_node = fields.require("boundary")?;
+            let boundary = shape_ref(boundary_node, materials, shapes, base_dir, shutter)?;
+            let density_node = fields.require("density")?;
+            let density = as_f64(density_node)?;
+            if density <= 0.0 {
+                return Err(density_node.error("density must be positive"));
+            }
+            let phase_function = phase_material(&mut fields, base_dir)?;
+            Arc::new(ConstantMedium::with_phase_function(boundary, density, phase_function))
+        }
+        "heterogeneous_medium" => {
+            let density_node = fields.require("density")?;
+            let (field, grid_box) = parse_density_field(density_node, base_dir)?;
+            let boundary = match (fields.get("boundary"), grid_box) {
+                (Some(node), _) => shape_ref(node, materials, shapes, base_dir, shutter)?,
+                (None, Some(bbox)) => {
+                    // The box's own material is never used; only its surface is.
+                    let unused: Arc<dyn Material> = Arc::new(Isotropic::new(Color::new(1.0, 1.0, 1.0)));
+                    let a = Point3::new(bbox.x.min, bbox.y.min, bbox.z.min);
+                    let b = Point3::new(bbox.x.max, bbox.y.max, bbox.z.max);
+                    Arc::new(make_box(a, b, unused))
+                }
+                (None, None) => return Err(node.error("noise densities need a \"boundary\"")),
+            };
+            let phase_function = phase_material(&mut fields, base_dir)?;
+            Arc::new(HeterogeneousMedium::with_phase_function(boundary, field, phase_function))
+        }
+        other => return Err(kind.error(format!("unknown object type \"{other}\""))),
+    };
+    fields.finish()?;
+    Ok(object)
+}
+
+/// Builds one punctual light.
+fn parse_light(node: &Node) -> Result<Arc<dyn Light>, SceneError> {
+    let mut fields = Fields::new(node)?;
+    let kind = fields.require("type")?;
+    let light: Arc<dyn Light> = match as_str(kind)? {
+        "point" => {
+            let position = fields.vec3("position")?;
+            Arc::new(PointLight::new(position, fields.color("intensity")?))
+        }
+        "spot" => {
+            let position = fields.vec3("position")?;
+            let direction = light_direction(&mut fields)?;
+            let intensity = fields.color("intensity")?;
+            let cone_node = fields.require("cone_angle")?;
+            let cone_angle = as_f64(cone_node)?;
+            if !(cone_angle > 0.0 && cone_angle <= 180.0) {
+                return Err(cone_node.error("cone angle must be between 0 and 180 degrees"));
+            }
+            let falloff_start = fields.f64_or("falloff_start", cone_angle)?;
+            Arc::new(SpotLight::new(position, direction, intensity, cone_angle, falloff_start))
+        }
+        "directional" => {
+            let direction = light_direction(&mut fields)?;
+            Arc::new(DirectionalLight::new(direction, fields.color("irradiance")?))
+        }
+        other => return Err(kind.error(format!("unknown light type \"{other}\""))),
+    };
+    fields.finish()?;
+    Ok(light)
+}
+
+fn light_direction(fields: &mut Fields) -> Result<Vec3, SceneError> {
+    let node = fields.require("direction")?;
+    let direction = as_vec3(node)?;
+    if direction.near_zero() {
+        return Err(node.error("direction must be non-zero"));
+    }
+    Ok(direction)
+}
+
+/// The phase-function material of a medium: isotropic, or Henyey-Greenstein
+/// when an `anisotropy` is given.
+fn phase_material(fields: &mut Fields, base_dir: &Path) -> Result<Arc<dyn Material>, SceneError> {
+    let albedo = texture_ref(fields.require("albedo")?, base_dir)?;
+    Ok(match fields.get("anisotropy") {
+        Some(node) => {
+            let g = as_f64(node)?;
+            if !(-1.0 < g && g < 1.0) {
+                return Err(node.error("anisotropy must be between -1 and 1"));
+            }
+            Arc::new(Anisotropic::from_texture(albedo, g))
+        }
+        None => Arc::new(Isotropic::from_texture(albedo)),
+    })
+}
+
+/// A density field, along with the box it spans when it has one.
+fn parse_density_field(
+    node: &Node,
+    base_dir: &Path,
+) -> Result<(Arc<dyn DensityField>, Option<Aabb>), SceneError> {
+    let mut fields = Fields::new(node)?;
+    let kind = fields.require("type")?;
+    let result: (Arc<dyn DensityField>, _) = match as_str(kind)? {
+        "grid" => {
+            let path_node = fields.require("path")?;
+            let path = base_dir.join(as_str(path_node)?);
+            let grid = VoxelGrid::load(&path)
+                .map_err(|err| path_node.error(format!("{}: {err}", path.display())))?;
+            let bbox = grid.bounding_box();
+            (Arc::new(grid), Some(bbox))
+        }
+        "noise" => {
+            let max_density_node = fields.require("max_density")?;
+            let max_density = as_f64(max_density_node)?;
+            if max_density <= 0.0 {
+                return Err(max_density_node.error("max_density must be positive"));
+            }
+            let scale = match fields.get("scale") {
+                Some(scale_node) => {
+                    let scale = as_f64(scale_node)?;
+                    if scale <= 0.0 {
+                        return Err(scale_node.error("noise scale must be positive"));
+                    }
+                    scale
+                }
+                None => 1.0,
+            };
+            let seed = match fields.get("seed") {
+                Some(node) => as_u64(node)?,
+                None => 0,
+            };
+            (Arc::new(NoiseDensity::new(max_density, scale, seed)), None)
+        }
+        other => return Err(kind.error(format!("unknown density type \"{other}\""))),
+    };
+    fields.finish()?;
+    Ok(result)
+}
+
+/// An object given either by shape name or with an inline definition.
+fn shape_ref(
+    node: &Node,
+    materials: &HashMap<&str, Arc<dyn Material>>,
+    shapes: &HashMap<&str, Arc<dyn Hittable>>,
+    base_dir: &Path,
+    shutter: Interval,
+) -> Result<Arc<dyn Hittable>, SceneError> {
+    match &node.value {
+        Value::String(name) => shapes
+            .get(name.as_str())
+            .cloned()
+            .ok_or_else(|| node.error(format!("undefined shape \"{name}\""))),
+        _ => parse_object(node, materials, shapes, base_dir, shutter),
+    }
+}
+
+/// Composes a list of transform steps, each applied after the ones before
+/// it, into one object-to-world matrix.
+fn parse_transform(node: &Node) -> Result<Mat4, SceneError> {
+    let mut matrix = Mat4::identity();
+    for step in as_array(node)? {
+        let [(kind, value)] = as_object(step)? else {
+            return Err(step.error("expected an object with a single transform step"));
+        };
+        let step_matrix = match kind.as_str() {
+            "translate" => Mat4::translation(as_vec3(value)?),
+            "rotate_x" => Mat4::rotation_x(as_f64(value)?),
+            "rotate_y" => Mat4::rotation_y(as_f64(value)?),
+            "rotate_z" => Mat4::rotation_z(as_f64(value)?),
+            "scale" => match &value.value {
+                Value::Number(_) => {
+                    let factor = as_f64(value)?;
+                    Mat4::scaling(Vec3::new(factor, factor, factor))
+                }
+                _ => Mat4::scaling(as_vec3(value)?),
+            },
+            "matrix" => {
+                let rows = as_array(value)?;
+                if rows.len() != 4 {
+                    return Err(value.error("expected a matrix of 4 rows"));
+                }
+                let mut m = [[0.0; 4]; 4];
+                for (row, row_node) in m.iter_mut().zip(rows) {
+                    let items = as_array(row_node)?;
+                    if items.len() != 4 {
+                        return Err(row_node.error("expected a row of 4 numbers"));
+                    }
+                    for (value, item) in row.iter_mut().zip(items) {
+                        *value = as_f64(item)?;
+                    }
+                }
+                Mat4::new(m)
+            }
+            other => return Err(step.error(format!("unknown transform \"{other}\""))),
+        };
+        matrix = step_matrix * matrix;
+    }
+    Ok(matrix)
+}
+
+/// A texture given either as a plain color or as a texture definition.
+fn texture_ref(node: &Node, base_dir: &Path) -> Result<Arc<dyn Texture>, SceneError> {
+    match &node.value {
+        Value::Array(_) => Ok(Arc::new(SolidColor::new(as_vec3(node)?))),
+        Value::Object(_) => parse_texture(node, base_dir),
+        _ => Err(node.error(format!(
+            "expected a color or texture definition, found {}",
+            node.kind()
+        ))),
+    }
+}
+
+fn parse_texture(node: &Node, base_dir: &Path) -> Result<Arc<dyn Texture>, SceneError> {
+    let mut fields = Fields::new(node)?;
+    let kind = fields.require("type")?;
+    let texture: Arc<dyn Texture> = match as_str(kind)? {
+        "solid" => Arc::new(SolidColor::new(fields.color("color")?)),
+        "checker" => {
+            let scale_node = fields.require("scale")?;
+            let scale = as_f64(scale_node)?;
+            if scale <= 0.0 {
+                return Err(scale_node.error("checker scale must be positive"));
+            }
+            let even = texture_ref(fields.require("even")?, base_dir)?;
+            let odd = texture_ref(fields.require("odd")?, base_dir)?;
+            Arc::new(CheckerTexture::new(scale, even, odd))
+        }
+        "image" => {
+            let path_node = fields.require("path")?;
+            let path = base_dir.join(as_str(path_node)?);
+            let texture = ImageTexture::load(&path)
+                .map_err(|err| path_node.error(format!("{}: {err}", path.display())))?;
+            Arc::new(texture)
+        }
+        "noise" => {
+            let style = match fields.get("style") {
+                None => NoiseStyle::Marble,
+                Some(node) => match as_str(node)? {
+                    "smooth" => NoiseStyle::Smooth,
+                    "turbulence" => NoiseStyle::Turbulence,
+                    "marble" => NoiseStyle::Marble,
+                    other => return Err(node.error(format!("unknown noise style \"{other}\""))),
+                },
+            };
+            let scale = fields.f64_or("scale", 1.0)?;
+            let seed = match fields.get("seed") {
+                Some(node) => as_u64(node)?,
+                None => 0,
+            };
+            Arc::new(NoiseTexture::new(style, scale, seed))
+        }
+        other => return Err(kind.error(format!("unknown texture type \"{other}\""))),
+    };
+    fields.finish()?;
+    Ok(texture)
+}
+
+fn material_ref(
+    node: &Node,
+    materials: &HashMap<&str, Arc<dyn Material>>,
+    base_dir: &Path,
+) -> Result<Arc<dyn Material>, SceneError> {
+    match &node.value {
+        Value::String(name) => materials
+            .get(name.as_str())
+            .cloned()
+            .ok_or_else(|| node.error(format!("undefined material \"{name}\""))),
+        Value::Object(_) => parse_material(node, base_dir),
+        _ => Err(node.error(format!(
+            "expected a material name or definition, found {}",
+            node.kind()
+        ))),
+    }
+}
+
+fn as_object(node: &Node) -> Result<&[(String, Node)], SceneError> {
+    match &node.value {
+        Value::Object(entries) => Ok(entries),
+        _ => Err(node.error(format!("expected an object, found {}", node.kind()))),
+    }
+}
+
+fn as_array(node: &Node) -> Result<&[Node], SceneError> {
+    match &node.value {
+        Value::Array(items) => Ok(items),
+        _ => Err(node.error(format!("expected an array, found {}", node.kind()))),
+    }
+}
+
+fn as_str(node: &Node) -> Result<&str, SceneError> {
+    match &node.value {
+        Value::String(s) => Ok(s),
+        _ => Err(node.error(format!("expected a string, found {}", node.kind()))),
+    }
+}
+
+fn as_f64(node: &Node) -> Result<f64, SceneError> {
+    match node.value {
+        Value::Number(n) => Ok(n),
+        _ => Err(node.error(format!("expected a number, found {}", node.kind()))),
+    }
+}
+
+fn as_u64(node: &Node) -> Result<u64, SceneError> {
+    let n = as_f64(node)?;
+    if n < 0.0 || n.fract() != 0.0 || n > u64::MAX as f64 {
+        return Err(node.error(format!("expected a non-negative integer, found {n}")));
+    }
+    Ok(n as u64)
+}
+
+fn as_vec3(node: &Node) -> Result<Vec3, SceneError> {
+    match as_array(node)? {
+        [x, y, z] => Ok(Vec3::new(as_f64(x)?, as_f64(y)?, as_f64(z)?)),
+        items => Err(node.error(format!(
+            "expected an array of 3 numbers, found {} elements",
+            items.len()
+        ))),
+    }
+}
+
+fn triple(node: &Node) -> Result<&[Node; 3], SceneError> {
+    let items = as_array(node)?;
+    items.try_into().map_err(|_| {
+        node.error(format!(
+            "expected an array of 3 elements, found {} elements",
+            items.len()
+        ))
+    })
+}
+
+fn vec3_triple(node: &Node) -> Result<[Vec3; 3], SceneError> {
+    let [a, b, c] = triple(node)?;
+    Ok([as_vec3(a)?, as_vec3(b)?, as_vec3(c)?])
+}
+
+fn as_uv(node: &Node) -> Result<(f64, f64), SceneError> {
+    match as_array(node)? {
+        [u, v] => Ok((as_f64(u)?, as_f64(v)?)),
+        items => Err(node.error(format!(
+            "expected an array of 2 numbers, found {} elements",
+            items.len()
+        ))),
+    }
+}
+
+/// The members of a JSON object, tracking which ones have been read so that
+/// misspelled or unsupported keys are reported instead of silently ignored.
+struct Fields<'a> {
+    node: &'a Node,
+    entries: &'a [(String, Node)],
+    used: Vec<bool>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(node: &'a Node) -> Result<Self, SceneError> {
+        let entries = as_object(node)?;
+        Ok(Fields {
+            node,
+            entries,
+            used: vec![false; entries.len()],
+        })
+    }
+
+    fn get(&mut self, key: &str) -> Option<&'a Node> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        self.used[index] = true;
+        Some(&self.entries[index].1)
+    }
+
+    fn require(&mut self, key: &str) -> Result<&'a Node, SceneError> {
+        self.get(key)
+            .ok_or_else(|| self.node.error(format!("missing required field \"{key}\"")))
+    }
+
+    fn f64(&mut self, key: &str) -> Result<f64, SceneError> {
+        as_f64(self.require(key)?)
+    }
+
+    fn f64_or(&mut self, key: &str, default: f64) -> Result<f64, SceneError> {
+        self.get(key).map_or(Ok(default), as_f64)
+    }
+
+    fn vec3(&mut self, key: &str) -> Result<Vec3, SceneError> {
+        as_vec3(self.require(key)?)
+    }
+
+    fn color(&mut self, key: &str) -> Result<Color, SceneError> {
+        self.vec3(key)
+    }
+
+    fn finish(self) -> Result<(), SceneError> {
+        match self.used.iter().position(|used| !used) {
+            Some(index) => {
+                let (key, node) = &self.entries[index];
+                Err(node.error(format!("unknown field \"{key}\"")))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+
+    fn error_message(src: &str) -> String {
+        match parse_scene(src) {
+            Err(err) => err.to_string(),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn builds_world_and_camera() {
+        let scene = parse_scene(
+            r#"{
+                "camera": { "image_width": 64, "aspect_ratio": 2.0, "vfov": 40,
+                            "shutter_open": 0.25, "shutter_close": 0.75 },
+                "background": [0, 0, 0],
+                "materials": {
+                    "ground": { "type": "lambertian", "albedo": {
+                        "type": "checker", "scale": 0.5,
+                        "even": [0.2, 0.3, 0.1], "odd": { "type": "solid", "color": [0.9, 0.9, 0.9] } } },
+                    "marble": { "type": "lambertian",
+                                "albedo": { "type": "noise", "scale": 4, "seed": 2 } },
+                    "steel": { "type": "metal", "albedo": [0.7, 0.6, 0.5], "fuzz": 0.1 },
+                    "lamp": { "type": "diffuse_light", "emit": [4, 4, 4] }
+                },
+                "shapes": {
+                    "crate": { "type": "box", "a": [0, 0, 0], "b": [1, 1, 1], "material": "ground" }
+                },
+                "lights": [
+                    { "type": "point", "position": [0, 4, 0], "intensity": [10, 10, 10] },
+                    { "type": "spot", "position": [0, 4, 0], "direction": [0, -1, 0],
+                      "intensity": [50, 50, 50], "cone_angle": 30, "falloff_start": 20 },
+                    { "type": "directional", "direction": [-1, -2, 0], "irradiance": [2, 2, 1.8] }
+                ],
+                "objects": [
+                    { "type": "instance", "shape": "crate",
+                      "transform": [{ "scale": [1, 2, 1] }, { "rotate_y": 15 }, { "translate": [-2, 0, 3] }] },
+                    { "type": "instance", "shape": "crate", "transform": [{ "translate": [3, 0, -2] }] },
+                    { "type": "constant_medium", "boundary": "crate", "density": 0.5, "albedo": [1, 1, 1] },
+                    { "type": "instance",
+                      "shape": { "type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "steel" },
+                      "transform": [{ "matrix": [[2, 0, 0, 1], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]] }] },
+                    { "type": "sphere", "center": [0, -1000, 0], "radius": 1000, "material": "ground" },
+                    { "type": "sphere", "center": [4, 1, 0], "center1": [4, 1.5, 0], "radius": 1, "material": "steel" },
+                    { "type": "sphere", "center": [0, 5, 0], "radius": 1, "material": "lamp" },
+                    { "type": "quad", "q": [0, 0, 0], "u": [1, 0, 0], "v": [0, 0, 1], "material": "ground" },
+                    { "type": "box", "a": [2, 0, 2], "b": [3, 1, 3], "material": "steel" },
+                    { "type": "triangle", "vertices": [[0, 0, 0], [1, 0, 0], [0, 1, 0]],
+                      "normals": [[0, 0, 1], [0, 0, 1], [0, 0, 1]], "uvs": [[0, 0], [1, 0], [0, 1]],
+                      "material": "ground" },
+                    { "type": "sphere", "center": [0, 1, 0], "radius": 1,
+                      "material": { "type": "dielectric", "refraction_index": 1.5 } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let camera = scene.camera.build().unwrap();
+        assert_eq!(scene.world.objects().len(), 11);
+        assert_eq!(scene.lights.area().objects().len(), 1);
+        assert_eq!(scene.lights.punctual().len(), 3);
+        assert_eq!((camera.image_width(), camera.image_height()), (64, 32));
+    }
+
+    #[test]
+    fn emissive_surfaces_become_area_lights() {
+        let scene = parse_scene(
+            r#"{
+                "materials": { "lamp": { "type": "diffuse_light", "emit": [4, 4, 4] } },
+                "shapes": { "panel": { "type": "quad", "q": [0, 0, 0], "u": [1, 0, 0], "v": [0, 0, 1],
+                                       "material": "lamp" } },
+                "objects": [
+                    { "type": "box", "a": [-1, 4, -1], "b": [1, 5, 1], "material": "lamp" },
+                    { "type": "triangle", "vertices": [[4, 4, 0], [5, 4, 0], [4, 4, 1]], "material": "lamp" },
+                    { "type": "instance", "shape": "panel", "transform": [{ "translate": [-5, 4, 0] }] },
+                    { "type": "constant_medium", "boundary": "panel", "density": 1, "albedo": [1, 1, 1] },
+                    { "type": "sphere", "center": [0, 0, 0], "radius": 1,
+                      "material": { "type": "lambertian", "albedo": [0.5, 0.5, 0.5] } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let area = scene.lights.area();
+        assert_eq!(area.objects().len(), 3);
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        for target in [Point3::new(0.0, 4.0, 0.0), Point3::new(4.2, 4.0, 0.2), Point3::new(-4.5, 4.0, 0.5)] {
+            assert!(area.pdf_value(origin, target - origin, 0.0) > 0.0, "{target:?}");
+        }
+        assert_eq!(area.pdf_value(origin, Vec3::new(0.0, -1.0, 0.0), 0.0), 0.0);
+    }
+
+    #[test]
+    fn moving_spheres_travel_while_the_shutter_is_open() {
+        let scene = parse_scene(
+            r#"{
+                "camera": { "shutter_open": 2, "shutter_close": 3 },
+                "objects": [
+                    { "type": "sphere", "center": [0, 0, 0], "center1": [4, 0, 0], "radius": 1,
+                      "material": { "type": "lambertian", "albedo": [0.5, 0.5, 0.5] } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let hits = |x: f64, time: f64| {
+            let r = Ray::with_time(Point3::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), time);
+            scene.world.hit(&r, Interval::new(0.001, f64::INFINITY)).is_some()
+        };
+        assert!(hits(0.0, 2.0) && !hits(4.0, 2.0));
+        assert!(hits(2.0, 2.5) && !hits(0.0, 2.5));
+        assert!(hits(4.0, 3.0) && !hits(0.0, 3.0));
+    }
+
+    #[test]
+    fn reports_semantic_errors_with_location() {
+        assert_eq!(
+            error_message("{\n  \"objects\": [\n    { \"type\": \"sphere\", \"center\": [0, 0, 0], \"radius\": 1, \"material\": \"glass\" }\n  ]\n}"),
+            "line 3, column 71: undefined material \"glass\""
+        );
+        assert_eq!(
+            error_message("{ \"materials\": { \"m\": { \"type\": \"plastic\" } } }"),
+            "line 1, column 33: unknown material type \"plastic\""
+        );
+        assert_eq!(
+            error_message("{ \"materials\": { \"m\": { \"type\": \"metal\", \"albedo\": { \"type\": \"checker\", \"scale\": 0, \"even\": [0, 0, 0], \"odd\": [1, 1, 1] } } } }"),
+            "line 1, column 82: checker scale must be positive"
+        );
+        assert_eq!(
+            error_message("{ \"materials\": { \"m\": { \"type\": \"lambertian\", \"albedo\": \"red\" } } }"),
+            "line 1, column 57: expected a color or texture definition, found a string"
+        );
+        assert_eq!(
+            error_message("{ \"materials\": { \"m\": { \"type\": \"conductor\", \"metal\": \"tin\" } } }"),
+            "line 1, column 55: unknown metal \"tin\""
+        );
+        assert_eq!(
+            error_message("{ \"materials\": { \"m\": { \"type\": \"conductor\", \"metal\": \"gold\", \"roughness_u\": 1.5 } } }"),
+            "line 1, column 78: roughness must be between 0 and 1"
+        );
+        assert_eq!(
+            error_message("{ \"camera\": { \"vfov\": 190 } }"),
+            "line 1, column 13: vertical field of view must be between 0 and 180 degrees, got 190"
+        );
+        assert_eq!(
+            error_message("{ \"camera\": { \"fov\": 30 } }"),
+            "line 1, column 22: unknown field \"fov\""
+        );
+        assert_eq!(
+            error_message("{ \"objects\": [ { \"type\": \"sphere\", \"center\": [0, 0], \"radius\": 1, \"material\": {} } ] }"),
+            "line 1, column 46: expected an array of 3 numbers, found 2 elements"
+        );
+        assert_eq!(
+            error_message("{ \"objects\": [ { \"type\": \"instance\", \"shape\": \"tree\", \"transform\": [] } ] }"),
+            "line 1, column 47: undefined shape \"tree\""
+        );
+        assert_eq!(
+            error_message("{ \"shapes\": { \"s\": { \"type\": \"box\", \"a\": [0, 0, 0], \"b\": [1, 1, 1], \"material\": {\"type\": \"lambertian\", \"albedo\": [1, 1, 1]} } }, \"objects\": [ { \"type\": \"instance\", \"shape\": \"s\", \"transform\": [{ \"scale\": 0 }] } ] }"),
+            "line 1, column 192: transform must be affine and invertible"
+        );
+        assert_eq!(
+            error_message("{ \"objects\": [ { \"type\": \"instance\", \"transform\": [{ \"shear\": 1 }], \"shape\": \"s\" } ] }"),
+            "line 1, column 52: unknown transform \"shear\""
+        );
+        assert!(
+            error_message("{ \"objects\": [ { \"type\": \"mesh\", \"path\": \"missing.obj\" } ] }")
+                .starts_with("line 1, column 42: missing.obj: ")
+        );
+        assert_eq!(
+            error_message("{ \"lights\": [ { \"type\": \"spot\", \"position\": [0, 1, 0], \"direction\": [0, -1, 0], \"intensity\": [1, 1, 1], \"cone_angle\": 0 } ] }"),
+            "line 1, column 119: cone angle must be between 0 and 180 degrees"
+        );
+        assert_eq!(
+            error_message("{ \"lights\": [ { \"type\": \"directional\", \"direction\": [0, 0, 0], \"irradiance\": [1, 1, 1] } ] }"),
+            "line 1, column 53: direction must be non-zero"
+        );
+    }
+
+    #[test]
+    fn loads_voxel_grids_next_to_the_scene_file() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/volumes.json");
+        let scene = load_scene(path).unwrap();
+        assert_eq!(scene.world.objects().len(), 3);
+        assert_eq!(
+            error_message("{ \"objects\": [ { \"type\": \"heterogeneous_medium\", \"density\": { \"type\": \"noise\", \"max_density\": 1 }, \"albedo\": [1, 1, 1] } ] }"),
+            "line 1, column 16: noise densities need a \"boundary\""
+        );
+        assert_eq!(
+            error_message("{ \"objects\": [ { \"type\": \"heterogeneous_medium\", \"boundary\": { \"type\": \"sphere\", \"center\": [0, 0, 0], \"radius\": 1, \"material\": { \"type\": \"lambertian\", \"albedo\": [1, 1, 1] } }, \"density\": { \"type\": \"noise\", \"max_density\": 0 }, \"albedo\": [1, 1, 1] } ] }"),
+            "line 1, column 222: max_density must be positive"
+        );
+        assert_eq!(
+            error_message("{ \"objects\": [ { \"type\": \"heterogeneous_medium\", \"boundary\": { \"type\": \"sphere\", \"center\": [0, 0, 0], \"radius\": 1, \"material\": { \"type\": \"lambertian\", \"albedo\": [1, 1, 1] } }, \"density\": { \"type\": \"noise\", \"max_density\": 1, \"scale\": -2 }, \"albedo\": [1, 1, 1] } ] }"),
+            "line 1, column 234: noise scale must be positive"
+        );
+    }
+
+    #[test]
+    fn parses_environment_and_daylight_backgrounds() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/environment.json");
+        let scene = load_scene(path).unwrap();
+        assert_eq!(scene.world.objects().len(), 4);
+        assert!(scene.lights.is_empty());
+        assert_eq!(
+            error_message("{ \"background\": { \"type\": \"studio\" } }"),
+            "line 1, column 27: unknown background type \"studio\""
+        );
+        assert!(
+            error_message("{ \"background\": { \"type\": \"environment\", \"path\": \"missing.hdr\" } }")
+                .starts_with("line 1, column 50: missing.hdr: ")
+        );
+        assert_eq!(
+            error_message("{ \"background\": { \"type\": \"daylight\", \"sun_elevation\": -5 } }"),
+            "line 1, column 56: sun elevation must be between 0 and 90 degrees"
+        );
+        assert_eq!(
+            error_message("{ \"background\": { \"type\": \"daylight\", \"sun_elevation\": 30, \"turbidity\": 1 } }"),
+            "line 1, column 73: turbidity must be between 2 and 10"
+        );
+    }
+
+    #[test]
+    fn mesh_paths_resolve_against_the_scene_file() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/pyramid.json");
+        let scene = load_scene(path).unwrap();
+        assert_eq!(scene.world.objects().len(), 4);
+    }
+}
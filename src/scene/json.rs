@@ -0,0 +1,372 @@
+//! A small JSON reader that remembers where every value came from, so scene
+//! errors can point at the offending line and column.
+
+use super::SceneError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub value: Value,
+    pub pos: Position,
+}
+
+impl Node {
+    pub fn error(&self, message: impl Into<String>) -> SceneError {
+        SceneError::Parse {
+            line: self.pos.line,
+            column: self.pos.column,
+            message: message.into(),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self.value {
+            Value::Null => "null",
+            Value::Bool(_) => "a boolean",
+            Value::Number(_) => "a number",
+            Value::String(_) => "a string",
+            Value::Array(_) => "an array",
+            Value::Object(_) => "an object",
+        }
+    }
+}
+
+pub fn parse(src: &str) -> Result<Node, SceneError> {
+    let mut parser = Parser {
+        chars: src.chars().collect(),
+        index: 0,
+        pos: Position { line: 1, column: 1 },
+    };
+
+    parser.skip_whitespace();
+    let node = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.peek().is_some() {
+        return Err(parser.error("unexpected trailing characters"));
+    }
+    Ok(node)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+    pos: Position,
+}
+
+impl Parser {
+    fn error(&self, message: impl Into<String>) -> SceneError {
+        SceneError::Parse {
+            line: self.pos.line,
+            column: self.pos.column,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.index += 1;
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
+        }
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SceneError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => Err(self.error(format!("expected '{expected}', found '{c}'"))),
+            None => Err(self.error(format!("expected '{expected}', found end of input"))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.bump();
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Node, SceneError> {
+        let pos = self.pos;
+        let value = match self.peek() {
+            Some('{') => self.parse_object()?,
+            Some('[') => self.parse_array()?,
+            Some('"') => Value::String(self.parse_string()?),
+            Some('-' | '0'..='9') => self.parse_number()?,
+            Some('t') => self.parse_literal("true", Value::Bool(true))?,
+            Some('f') => self.parse_literal("false", Value::Bool(false))?,
+            Some('n') => self.parse_literal("null", Value::Null)?,
+            Some(c) => return Err(self.error(format!("unexpected character '{c}'"))),
+            None => return Err(self.error("unexpected end of input")),
+        };
+        Ok(Node { value, pos })
+    }
+
+    fn parse_literal(&mut self, word: &str, value: Value) -> Result<Value, SceneError> {
+        let start = self.pos;
+        for expected in word.chars() {
+            if self.peek() != Some(expected) {
+                return Err(SceneError::Parse {
+                    line: start.line,
+                    column: start.column,
+                    message: format!("invalid literal, expected '{word}'"),
+                });
+            }
+            self.bump();
+        }
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<Value, SceneError> {
+        let start = self.pos;
+        let begin = self.index;
+        while matches!(self.peek(), Some('-' | '+' | '.' | 'e' | 'E' | '0'..='9')) {
+            self.bump();
+        }
+        let text: String = self.chars[begin..self.index].iter().collect();
+
+        if is_json_number(&text)
+            && let Ok(n) = text.parse::<f64>()
+        {
+            return Ok(Value::Number(n));
+        }
+        Err(SceneError::Parse {
+            line: start.line,
+            column: start.column,
+            message: format!("invalid number '{text}'"),
+        })
+    }
+
+    fn parse_string(&mut self) -> Result<String, SceneError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let c = self
+                .bump()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escape = self.bump().ok_or_else(|| self.error("unterminated string"))?;
+                    match escape {
+                        '"' => s.push('"'),
+                        '\\' => s.push('\\'),
+                        '/' => s.push('/'),
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'u' => s.push(self.parse_unicode_escape()?),
+                        other => return Err(self.error(format!("invalid escape '\\{other}'"))),
+                    }
+                }
+                c if (c as u32) < 0x20 => {
+                    return Err(self.error("control character in string"));
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, SceneError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .peek()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("invalid unicode escape"))?;
+            self.bump();
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char, SceneError> {
+        let high = self.parse_hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if self.bump() != Some('\\') || self.bump() != Some('u') {
+                return Err(self.error("unpaired surrogate in unicode escape"));
+            }
+            let low = self.parse_hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("unpaired surrogate in unicode escape"));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn parse_array(&mut self) -> Result<Value, SceneError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(Value::Array(items));
+        }
+
+        loop {
+            self.skip_whitespace();
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some(']') => {
+                    self.bump();
+                    return Ok(Value::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']' in array")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Value, SceneError> {
+        self.expect('{')?;
+        let mut entries: Vec<(String, Node)> = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(Value::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key_pos = self.pos;
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.parse_string()?;
+            if entries.iter().any(|(k, _)| *k == key) {
+                return Err(SceneError::Parse {
+                    line: key_pos.line,
+                    column: key_pos.column,
+                    message: format!("duplicate key \"{key}\""),
+                });
+            }
+
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            let value = self.parse_value()?;
+            entries.push((key, value));
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some('}') => {
+                    self.bump();
+                    return Ok(Value::Object(entries));
+                }
+                _ => return Err(self.error("expected ',' or '}' in object")),
+            }
+        }
+    }
+}
+
+/// Checks `text` against the JSON number grammar, which is stricter than
+/// what `f64::from_str` accepts (no leading `+`, no leading zeros, digits on
+/// both sides of the decimal point).
+fn is_json_number(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+
+    if bytes.get(i) == Some(&b'-') {
+        i += 1;
+    }
+    match digits(&mut i) {
+        0 => return false,
+        n if n > 1 && bytes[i - n] == b'0' => return false,
+        _ => {}
+    }
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(bytes.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    i == bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_location(src: &str) -> (usize, usize) {
+        match parse(src) {
+            Err(SceneError::Parse { line, column, .. }) => (line, column),
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_nested_values_with_positions() {
+        let node = parse("{\n  \"a\": [1, -2.5e1, true],\n  \"b\": \"x\\u00e9\"\n}").unwrap();
+        let Value::Object(entries) = &node.value else {
+            panic!("expected an object");
+        };
+        assert_eq!(entries[0].0, "a");
+        assert_eq!(entries[0].1.pos, Position { line: 2, column: 8 });
+        let Value::Array(items) = &entries[0].1.value else {
+            panic!("expected an array");
+        };
+        assert_eq!(items[1].value, Value::Number(-25.0));
+        assert_eq!(entries[1].1.value, Value::String("xé".to_string()));
+    }
+
+    #[test]
+    fn reports_line_and_column_of_syntax_errors() {
+        assert_eq!(error_location("{\n  \"a\": 1\n  \"b\": 2\n}"), (3, 3));
+        assert_eq!(error_location("[1, 2,\n  ]"), (2, 3));
+        assert_eq!(error_location("{\"a\": 01}"), (1, 7));
+        assert_eq!(error_location("{\"a\": tru}"), (1, 7));
+        assert_eq!(error_location("{\"a\": 1, \"a\": 2}"), (1, 10));
+        assert_eq!(error_location("[1] x"), (1, 5));
+    }
+}
@@ -0,0 +1,284 @@
+//! The Preetham, Shirley and Smits analytic daylight model: a clear sky lit
+//! by a sun at any position and through any haze.
+
+use crate::color::Color;
+use crate::onb::Onb;
+use crate::pdf::{Pdf, SpherePdf};
+use crate::sampler::Sampler;
+use crate::vec3::{dot, unit_vector, Vec3};
+use std::f64::consts::PI;
+
+/// Angular radius of the sun seen from the earth.
+const SUN_ANGULAR_RADIUS: f64 = 0.004_65;
+
+/// Illuminance of the sun above the atmosphere, in klx.
+const SOLAR_ILLUMINANCE: f64 = 128.0;
+
+/// Converts luminance in kcd/m² to the renderer's radiance units, so that a
+/// white surface facing an unfiltered sun reflects radiance 1.
+const LUMINANCE_SCALE: f64 = PI / SOLAR_ILLUMINANCE;
+
+/// How often [`Daylight::random`] aims at the sun rather than the sky.
+const SUN_SAMPLE_FRACTION: f64 = 0.5;
+
+/// Representative wavelengths, in micrometers, of the red, green and blue
+/// channels.
+const WAVELENGTHS: [f64; 3] = [0.68, 0.55, 0.44];
+
+/// The sky and the sun disk, for a sun `elevation` degrees above the horizon
+/// and `azimuth` degrees round from -z toward +x, seen through air of the
+/// given `turbidity`: 2 is a very clear day and 10 a hazy one.
+///
+/// The sun is part of the sky rather than a separate light: it is a disk of
+/// the real angular size, so it casts soft-edged shadows, shows up in
+/// reflections, and is sampled directly along with the rest of the sky.
+/// Below the horizon the sky keeps its horizon color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Daylight {
+    sun_direction: Vec3,
+    /// Perez distribution coefficients for luminance and the two
+    /// chromaticity coordinates.
+    perez: [[f64; 5]; 3],
+    /// Zenith luminance and chromaticity, divided by the Perez function at
+    /// the zenith so that evaluating a direction needs one multiply.
+    zenith: [f64; 3],
+    sun_radiance: Color,
+    cos_sun_radius: f64,
+    intensity: f64,
+}
+
+impl Daylight {
+    /// Elevations are clamped to [0, 90] degrees and turbidity to [2, 10],
+    /// the range the model was fitted over.
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64) -> Self {
+        let elevation = elevation.clamp(0.0, 90.0).to_radians();
+        let azimuth = azimuth.to_radians();
+        let t = turbidity.clamp(2.0, 10.0);
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let theta_s = PI / 2.0 - elevation;
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_chromaticity = |m: [[f64; 4]; 3]| {
+            let angles = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+            let row = |r: [f64; 4]| r.iter().zip(angles).map(|(a, b)| a * b).sum::<f64>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let zenith_x = zenith_chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = zenith_chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        let zenith = [zenith_luminance, zenith_x, zenith_y];
+        let zenith = std::array::from_fn(|i| zenith[i] / perez_function(&perez[i], 1.0, theta_s));
+
+        let cos_sun_radius = SUN_ANGULAR_RADIUS.cos();
+        let sun_solid_angle = 2.0 * PI * (1.0 - cos_sun_radius);
+        let transmittance = sun_transmittance(theta_s, t);
+        let sun_radiance = transmittance * (SOLAR_ILLUMINANCE * LUMINANCE_SCALE / sun_solid_angle);
+
+        Daylight {
+            sun_direction,
+            perez,
+            zenith,
+            sun_radiance,
+            cos_sun_radius,
+            intensity: 1.0,
+        }
+    }
+
+    /// Scales the radiance of both the sky and the sun.
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Unit vector toward the center of the sun.
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun_direction
+    }
+
+    /// Irradiance from the sun alone on a surface facing it.
+    pub fn sun_irradiance(&self) -> Color {
+        self.sun_radiance * (2.0 * PI * (1.0 - self.cos_sun_radius)) * self.intensity
+    }
+
+    /// Radiance arriving from `direction`, sky and sun together.
+    pub fn value(&self, direction: Vec3) -> Color {
+        let d = unit_vector(direction);
+        let cos_gamma = dot(d, self.sun_direction);
+        let mut color = if d.y() >= 0.0 {
+            self.sky(d.y(), cos_gamma)
+        } else {
+            // Look at the horizon straight above instead.
+            let horizon = Vec3::new(d.x(), 0.0, d.z());
+            let cos_gamma = if horizon.near_zero() {
+                0.0
+            } else {
+                dot(unit_vector(horizon), self.sun_direction)
+            };
+            self.sky(0.0, cos_gamma)
+        };
+        if cos_gamma >= self.cos_sun_radius {
+            color += self.sun_radiance;
+        }
+        color * self.intensity
+    }
+
+    /// Solid angle density of [`Daylight::random`] producing `direction`.
+    pub fn pdf_value(&self, direction: Vec3) -> f64 {
+        let mut pdf = (1.0 - SUN_SAMPLE_FRACTION) * SpherePdf.value(direction);
+        if dot(unit_vector(direction), self.sun_direction) >= self.cos_sun_radius {
+            pdf += SUN_SAMPLE_FRACTION / (2.0 * PI * (1.0 - self.cos_sun_radius));
+        }
+        pdf
+    }
+
+    /// A unit direction: half the time within the sun disk, and otherwise
+    /// anywhere, since the sky itself varies smoothly.
+    pub fn random(&self, sampler: &mut Sampler) -> Vec3 {
+        if sampler.random() >= SUN_SAMPLE_FRACTION {
+            return SpherePdf.generate(sampler);
+        }
+        let z = 1.0 + sampler.random() * (self.cos_sun_radius - 1.0);
+        let phi = 2.0 * PI * sampler.random();
+        let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+        let local = Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z);
+        unit_vector(Onb::new(self.sun_direction).transform(local))
+    }
+
+    /// Sky radiance for a direction `cos_theta` from the zenith and
+    /// `cos_gamma` from the sun.
+    fn sky(&self, cos_theta: f64, cos_gamma: f64) -> Color {
+        let gamma = cos_gamma.clamp(-1.0, 1.0).acos();
+        let [luminance, x, y] = std::array::from_fn(|i| {
+            self.zenith[i] * perez_function(&self.perez[i], cos_theta, gamma)
+        });
+        xyy_to_rgb(x, y, luminance * LUMINANCE_SCALE)
+    }
+}
+
+/// The Perez et al. sky distribution for a direction `cos_theta` from the
+/// zenith and `gamma` radians from the sun.
+fn perez_function(c: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+    // The model blows up right at the horizon.
+    let cos_theta = cos_theta.max(0.01);
+    (1.0 + c[0] * (c[1] / cos_theta).exp())
+        * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos().powi(2))
+}
+
+/// The fraction of sunlight in each channel that reaches the ground through
+/// Rayleigh scattering by air and Mie scattering by haze, for a sun
+/// `theta_s` radians from the zenith.
+fn sun_transmittance(theta_s: f64, turbidity: f64) -> Color {
+    // Relative optical mass of the air along the sun's path, which stays
+    // finite at the horizon.
+    let m = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let [r, g, b] = WAVELENGTHS.map(|lambda: f64| {
+        let rayleigh = -0.008735 * lambda.powf(-4.08) * m;
+        let aerosol = -beta * lambda.powf(-1.3) * m;
+        (rayleigh + aerosol).exp()
+    });
+    Color::new(r, g, b)
+}
+
+/// Converts CIE xyY to linear sRGB, dropping out-of-gamut negatives.
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0.0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Color::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sky_is_blue_overhead_and_brightest_around_the_sun() {
+        let sky = Daylight::new(30.0, 90.0, 3.0);
+        let sun = sky.sun_direction();
+        assert!((sun - Vec3::new(30f64.to_radians().cos(), 0.5, 0.0)).length() < 1e-12);
+
+        let zenith = sky.value(Vec3::new(0.0, 1.0, 0.0));
+        assert!(zenith.z() > zenith.x(), "{zenith:?}");
+        let near_sun = sky.value(Vec3::new(1.0, 0.7, 0.0));
+        let away = sky.value(Vec3::new(-1.0, 0.7, 0.0));
+        assert!(near_sun.y() > 2.0 * away.y());
+        // Below the horizon the sky keeps its horizon color.
+        assert_eq!(sky.value(Vec3::new(0.0, -0.5, -1.0)), sky.value(Vec3::new(0.0, 0.0, -1.0)));
+
+        // The disk is far brighter than any sky, and lower suns and hazier
+        // air are dimmer and redder.
+        assert!(sky.value(sun).y() > 1000.0 * near_sun.y());
+        let noon = Daylight::new(90.0, 0.0, 2.0).sun_irradiance();
+        assert!(noon.x() < PI && noon.y() > 0.7 * PI);
+        for dimmer in [sky.sun_irradiance(), Daylight::new(90.0, 0.0, 8.0).sun_irradiance()] {
+            assert!(dimmer.y() < noon.y());
+            assert!(dimmer.z() / dimmer.x() < noon.z() / noon.x());
+        }
+    }
+
+    #[test]
+    fn sampling_finds_the_sun_and_covers_the_sphere() {
+        let sky = Daylight::new(50.0, -20.0, 4.0).with_intensity(2.0);
+        let mut sampler = Sampler::new(5);
+        let (mut solid_angle, mut irradiance, mut in_sun) = (0.0, 0.0, 0);
+        let n = 20_000;
+        for _ in 0..n {
+            let direction = sky.random(&mut sampler);
+            let pdf = sky.pdf_value(direction);
+            assert!(pdf > 0.0);
+            solid_angle += 1.0 / pdf;
+            let cos_sun = dot(direction, sky.sun_direction());
+            if cos_sun >= sky.cos_sun_radius {
+                in_sun += 1;
+                irradiance += sky.sun_radiance.y() * sky.intensity * cos_sun / pdf;
+            }
+        }
+        assert!((solid_angle / n as f64 - 4.0 * PI).abs() < 0.3);
+        assert!((in_sun as f64 / n as f64 - SUN_SAMPLE_FRACTION).abs() < 0.02);
+        let expected = sky.sun_irradiance().y();
+        assert!((irradiance / n as f64 / expected - 1.0).abs() < 0.02);
+    }
+}
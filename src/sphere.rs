@@ -1,32 +1,71 @@
 use crate::{
+    aabb::Aabb,
     hit::{HitRecord, Hittable},
     interval::Interval,
     material::Material,
+    onb::Onb,
     ray::Ray,
+    sampler::Sampler,
     vec3::{Point3, Vec3, dot},
 };
-use std::rc::Rc;
+use std::f64::consts::PI;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Sphere {
-    center: Point3,
+    /// The center's path, from `center.at(0.0)` when `time` begins to
+    /// `center.at(1.0)` when it ends.
+    center: Ray,
+    time: Interval,
     radius: f64,
-    mat: Rc<dyn Material>,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
 }
 
 impl Sphere {
-    pub fn new(center: Point3, radius: f64, mat: Rc<dyn Material>) -> Self {
+    pub fn new(center: Point3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        Sphere::moving(center, center, Interval::new(0.0, 1.0), radius, mat)
+    }
+
+    /// A sphere whose center moves in a straight line from `center0` at
+    /// `time.min` to `center1` at `time.max`, normally the camera's shutter
+    /// interval. It rests at the ends outside that interval, so the bounding
+    /// box around both positions holds at every time.
+    pub fn moving(
+        center0: Point3,
+        center1: Point3,
+        time: Interval,
+        radius: f64,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let radius = radius.max(0.0);
+        let center = Ray::new(center0, center1 - center0);
+        let rvec = Vec3::new(radius, radius, radius);
+        let box0 = Aabb::from_points(center.at(0.0) - rvec, center.at(0.0) + rvec);
+        let box1 = Aabb::from_points(center.at(1.0) - rvec, center.at(1.0) + rvec);
         Sphere {
             center,
-            radius: radius.max(0.0),
+            time,
+            radius,
             mat,
+            bbox: Aabb::from_boxes(&box0, &box1),
         }
     }
+
+    fn center_at(&self, time: f64) -> Point3 {
+        let fraction = if self.time.size() > 0.0 {
+            ((time - self.time.min) / self.time.size()).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.center.at(fraction)
+    }
 }
 
 impl Hittable for Sphere {
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
-        let oc = self.center - r.origin();
+        let current_center = self.center_at(r.time());
+        let oc = current_center - r.origin();
 
         let a = r.direction().length_squared();
         let h = dot(r.direction(), oc);
@@ -46,17 +85,147 @@ impl Hittable for Sphere {
             }
         }
 
+        let p = r.at(root);
+        let outward_normal = (p - current_center) / self.radius;
+        let (u, v) = sphere_uv(outward_normal);
         let mut rec = HitRecord {
             t: root,
-            p: r.at(root),
+            p,
+            u,
+            v,
+            // u runs around the y axis; see `sphere_uv`.
+            tangent: Vec3::new(outward_normal.z(), 0.0, -outward_normal.x()),
             normal: Vec3::new(0.0, 0.0, 0.0),
             front_face: false,
             mat: self.mat.clone(),
         };
-
-        let outward_normal = (rec.p - self.center) / self.radius;
         rec.set_face_normal(r, outward_normal);
 
         Some(rec)
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    /// Directions are sampled uniformly over the cone the sphere subtends
+    /// from `origin`, with a moving sphere where it is at `time`.
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        let r = Ray::with_time(origin, direction, time);
+        if self.hit(&r, Interval::new(0.001, f64::INFINITY)).is_none() {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center_at(time) - origin).length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return 0.0;
+        }
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut Sampler) -> Vec3 {
+        let direction = self.center_at(time) - origin;
+        let distance_squared = direction.length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return direction;
+        }
+
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        let z = 1.0 + sampler.random() * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * sampler.random();
+        let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+        Onb::new(direction).transform(Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z))
+    }
+
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        self.mat.is_emissive().then(|| Arc::new(self.clone()) as Arc<dyn Hittable>)
+    }
+}
+
+/// Latitude-longitude coordinates of a point on the unit sphere: `u` runs
+/// around the y axis starting from -x, and `v` from the south pole (0) to
+/// the north pole (1).
+fn sphere_uv(p: Point3) -> (f64, f64) {
+    let theta = (-p.y()).acos();
+    let phi = (-p.z()).atan2(p.x()) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+
+    #[test]
+    fn moving_sphere_is_hit_where_it_is_at_the_ray_time() {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let (center0, center1) = (Point3::new(0.0, 0.0, 0.0), Point3::new(4.0, 0.0, 0.0));
+        for (open, close) in [(0.0, 1.0), (2.0, 3.0), (0.2, 0.4)] {
+            let time = Interval::new(open, close);
+            let sphere = Sphere::moving(center0, center1, time, 1.0, mat.clone());
+            let bbox = sphere.bounding_box();
+            assert_eq!((bbox.x.min, bbox.x.max), (-1.0, 5.0));
+
+            let dir = Vec3::new(0.0, 0.0, -1.0);
+            let t = Interval::new(0.001, f64::INFINITY);
+            let at = |x: f64, fraction: f64| {
+                let time = open + fraction * (close - open);
+                sphere.hit(&Ray::with_time(Point3::new(x, 0.0, 5.0), dir, time), t)
+            };
+            assert!(at(0.0, 0.0).is_some() && at(4.0, 0.0).is_none());
+            assert!(at(2.0, 0.5).is_some() && at(0.0, 0.5).is_none());
+            assert_eq!(at(4.0, 1.0).unwrap().normal, Vec3::new(0.0, 0.0, 1.0));
+            assert!(at(4.0, 3.0).is_some() && at(6.0, 3.0).is_none());
+            assert!(at(0.0, -1.0).is_some() && at(-2.0, -1.0).is_none());
+        }
+    }
+
+    #[test]
+    fn uv_follows_latitude_and_longitude() {
+        let cases = [
+            (Point3::new(1.0, 0.0, 0.0), (0.5, 0.5)),
+            (Point3::new(-1.0, 0.0, 0.0), (0.0, 0.5)),
+            (Point3::new(0.0, 0.0, 1.0), (0.25, 0.5)),
+            (Point3::new(0.0, 0.0, -1.0), (0.75, 0.5)),
+            (Point3::new(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (Point3::new(0.0, -1.0, 0.0), (0.5, 0.0)),
+        ];
+        for (p, (u, v)) in cases {
+            let (got_u, got_v) = sphere_uv(p);
+            assert!((got_u - u).abs() < 1e-12 && (got_v - v).abs() < 1e-12, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn light_samples_cover_the_subtended_cone() {
+        // The sphere passes (0, 3, 0) halfway through its move, at time 3.
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::moving(
+            Point3::new(-3.0, 3.0, 0.0),
+            Point3::new(3.0, 3.0, 0.0),
+            Interval::new(2.0, 4.0),
+            1.0,
+            mat,
+        );
+        let origin = Point3::new(0.5, 0.0, 0.0);
+        let mut sampler = Sampler::new(3);
+
+        let pdf = sphere.pdf_value(origin, sphere.random(origin, 3.0, &mut sampler), 3.0);
+        let distance_squared: f64 = 0.25 + 9.0;
+        let solid_angle = 2.0 * PI * (1.0 - (1.0 - 1.0 / distance_squared).sqrt());
+        assert!((pdf - 1.0 / solid_angle).abs() < 1e-9);
+        for _ in 0..1000 {
+            let direction = sphere.random(origin, 3.0, &mut sampler);
+            assert_eq!(sphere.pdf_value(origin, direction, 3.0), pdf);
+        }
+        assert_eq!(sphere.pdf_value(origin, Vec3::new(0.0, -1.0, 0.0), 3.0), 0.0);
+        assert_eq!(sphere.pdf_value(Point3::new(0.0, 3.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 3.0), 0.0);
+        // At time 2 the sphere is still at (-3, 3, 0), well away from where
+        // it is at time 3.
+        let toward_center = Vec3::new(-0.5, 3.0, 0.0);
+        assert_eq!(sphere.pdf_value(origin, toward_center, 2.0), 0.0);
+    }
 }
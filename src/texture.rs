@@ -0,0 +1,191 @@
+use crate::color::Color;
+use crate::image::Framebuffer;
+use crate::interval::Interval;
+use crate::perlin::Perlin;
+use crate::vec3::Point3;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A color that varies over a surface, looked up by the hit's surface
+/// coordinates `(u, v)` or its position `p`.
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        SolidColor { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.albedo
+    }
+}
+
+/// Alternates between two textures in a 3D grid of cubes `scale` wide.
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        CheckerTexture {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: f64, even: Color, odd: Color) -> Self {
+        CheckerTexture::new(
+            scale,
+            Arc::new(SolidColor::new(even)),
+            Arc::new(SolidColor::new(odd)),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let x = (self.inv_scale * p.x()).floor() as i64;
+        let y = (self.inv_scale * p.y()).floor() as i64;
+        let z = (self.inv_scale * p.z()).floor() as i64;
+
+        if (x + y + z) % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// Maps an image over the surface's `(u, v)` square, with `v = 1` at the
+/// top row of the image.
+pub struct ImageTexture {
+    image: Framebuffer,
+}
+
+impl ImageTexture {
+    pub fn new(image: Framebuffer) -> Self {
+        ImageTexture { image }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(ImageTexture::new(Framebuffer::load(path)?))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        let (width, height) = (self.image.width(), self.image.height());
+        if width == 0 || height == 0 {
+            // Solid cyan makes a missing image easy to spot.
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        let unit = Interval::new(0.0, 1.0);
+        let u = unit.clamp(u);
+        let v = 1.0 - unit.clamp(v);
+
+        let i = ((u * width as f64) as usize).min(width - 1);
+        let j = ((v * height as f64) as usize).min(height - 1);
+        self.image.get(i, j)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseStyle {
+    /// Plain Perlin noise remapped to [0, 1].
+    Smooth,
+    /// Seven octaves of turbulence, like a cloudy net. The octaves halve in
+    /// weight, so values stay below 2, though they seldom pass 1.
+    Turbulence,
+    /// Sine stripes along z, phase-shifted by turbulence.
+    Marble,
+}
+
+/// A grayscale Perlin noise texture sampled at `scale` times the hit
+/// position.
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f64,
+    style: NoiseStyle,
+}
+
+impl NoiseTexture {
+    pub fn new(style: NoiseStyle, scale: f64, seed: u64) -> Self {
+        NoiseTexture {
+            noise: Perlin::new(seed),
+            scale,
+            style,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let scaled = self.scale * *p;
+        let gray = match self.style {
+            NoiseStyle::Smooth => 0.5 * (1.0 + self.noise.noise(&scaled)),
+            NoiseStyle::Turbulence => self.noise.turb(&scaled, 7),
+            NoiseStyle::Marble => 0.5 * (1.0 + (scaled.z() + 10.0 * self.noise.turb(p, 7)).sin()),
+        };
+        Color::new(gray, gray, gray)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checker_alternates_between_cells() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let checker = CheckerTexture::from_colors(0.5, black, white);
+        assert_eq!(checker.value(0.0, 0.0, &Point3::new(0.1, 0.1, 0.1)), black);
+        assert_eq!(checker.value(0.0, 0.0, &Point3::new(0.6, 0.1, 0.1)), white);
+        assert_eq!(checker.value(0.0, 0.0, &Point3::new(-0.1, 0.1, 0.1)), white);
+        assert_eq!(checker.value(0.0, 0.0, &Point3::new(0.6, -0.1, 0.1)), black);
+    }
+
+    #[test]
+    fn image_texture_puts_v_one_at_the_top_row() {
+        let mut image = Framebuffer::new(2, 2);
+        let top_right = Color::new(1.0, 0.0, 0.0);
+        let bottom_left = Color::new(0.0, 0.0, 1.0);
+        image.set(1, 0, top_right);
+        image.set(0, 1, bottom_left);
+
+        let texture = ImageTexture::new(image);
+        let p = Point3::new(0.0, 0.0, 0.0);
+        assert_eq!(texture.value(0.9, 0.9, &p), top_right);
+        assert_eq!(texture.value(0.1, 0.1, &p), bottom_left);
+        assert_eq!(texture.value(1.5, -3.0, &p), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn noise_stays_in_the_range_of_its_style() {
+        for (style, max) in [
+            (NoiseStyle::Smooth, 1.0),
+            (NoiseStyle::Turbulence, 2.0),
+            (NoiseStyle::Marble, 1.0),
+        ] {
+            let texture = NoiseTexture::new(style, 4.0, 7);
+            for i in 0..100 {
+                let p = Point3::new(i as f64 * 0.13, 1.0 - i as f64 * 0.07, i as f64 * 0.29);
+                let c = texture.value(0.0, 0.0, &p);
+                assert!(c.x() >= 0.0 && c.x() <= max, "{style:?}: {c:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,437 @@
+//! Wrappers that place a [`Hittable`] in the world with an affine transform.
+//!
+//! Rays are carried into the object's own space, intersected there, and the
+//! hit point and normal are mapped back out. Because a wrapper only holds an
+//! `Arc` to the object, one mesh can be instanced any number of times
+//! without copying its geometry.
+
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    interval::Interval,
+    matrix::Mat4,
+    ray::Ray,
+    sampler::Sampler,
+    vec3::{cross, dot, unit_vector, Point3, Vec3},
+};
+use std::sync::Arc;
+
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    matrix: Mat4,
+    inverse: Mat4,
+    /// The inverse-transpose, which keeps normals perpendicular to the
+    /// surface under non-uniform scaling.
+    normal_matrix: Mat4,
+    /// How much the inverse scales volumes, which enters how it scales the
+    /// solid angles of light samples.
+    inverse_determinant: f64,
+    bbox: Aabb,
+}
+
+impl Transform {
+    /// Applies `matrix` (object space to world space) to `object`. Returns
+    /// `None` if the matrix is singular or not affine.
+    pub fn new(object: Arc<dyn Hittable>, matrix: Mat4) -> Option<Self> {
+        if !matrix.is_affine() {
+            return None;
+        }
+        let inverse = matrix.inverse()?;
+        let bbox = transform_box(&object.bounding_box(), &matrix);
+        let [x, y, z] = [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)]
+            .map(|axis| inverse.transform_vector(axis));
+        Some(Transform {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+            inverse_determinant: dot(x, cross(y, z)).abs(),
+            bbox,
+        })
+    }
+
+    pub fn matrix(&self) -> &Mat4 {
+        &self.matrix
+    }
+
+    /// `r` carried into object space. The direction is not renormalized, so
+    /// `t` means the same thing in both spaces.
+    fn object_ray(&self, r: &Ray) -> Ray {
+        Ray::with_time(
+            self.inverse.transform_point(r.origin()),
+            self.inverse.transform_vector(r.direction()),
+            r.time(),
+        )
+    }
+
+    fn to_world(&self, mut rec: HitRecord) -> HitRecord {
+        rec.p = self.matrix.transform_point(rec.p);
+        rec.tangent = self.matrix.transform_vector(rec.tangent);
+        rec.normal = unit_vector(self.normal_matrix.transform_vector(rec.normal));
+        rec
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let rec = self.object.hit(&self.object_ray(r), ray_t)?;
+        Some(self.to_world(rec))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn shadow_hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let rec = self.object.shadow_hit(&self.object_ray(r), ray_t)?;
+        Some(self.to_world(rec))
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval, sampler: &mut Sampler) -> f64 {
+        self.object.transmittance(&self.object_ray(r), ray_t, sampler)
+    }
+
+    /// The object's density for the direction carried into its space, times
+    /// the factor by which the inverse stretches solid angle around it:
+    /// `|det| / |d|^3` for a unit direction `d` mapped to length `|d|`.
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        let local = self.object_ray(&Ray::with_time(origin, unit_vector(direction), time));
+        let stretch = local.direction().length();
+        let pdf = self.object.pdf_value(local.origin(), local.direction(), time);
+        pdf * self.inverse_determinant / (stretch * stretch * stretch)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut Sampler) -> Vec3 {
+        let local_origin = self.inverse.transform_point(origin);
+        let direction = self.object.random(local_origin, time, sampler);
+        self.matrix.transform_vector(direction)
+    }
+
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        let emitters = Transform::new(self.object.emitters()?, self.matrix)?;
+        Some(Arc::new(emitters))
+    }
+}
+
+/// The world-space box around all eight transformed corners of `bbox`.
+fn transform_box(bbox: &Aabb, matrix: &Mat4) -> Aabb {
+    if bbox.x.min > bbox.x.max || bbox.y.min > bbox.y.max || bbox.z.min > bbox.z.max {
+        return Aabb::empty();
+    }
+
+    let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+    let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for corner in 0..8 {
+        let pick = |axis: usize| {
+            let interval = bbox.axis_interval(axis);
+            if corner & (1 << axis) == 0 { interval.min } else { interval.max }
+        };
+        let p = matrix.transform_point(Point3::new(pick(0), pick(1), pick(2)));
+        min = Point3::new(min.x().min(p.x()), min.y().min(p.y()), min.z().min(p.z()));
+        max = Point3::new(max.x().max(p.x()), max.y().max(p.y()), max.z().max(p.z()));
+    }
+    Aabb::from_points(min, max)
+}
+
+pub struct Translate(Transform);
+
+impl Translate {
+    pub fn new(object: Arc<dyn Hittable>, offset: Vec3) -> Self {
+        Translate(Transform::new(object, Mat4::translation(offset)).expect("translations are invertible"))
+    }
+}
+
+impl Hittable for Translate {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.0.hit(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.0.bounding_box()
+    }
+
+    fn shadow_hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.0.shadow_hit(r, ray_t)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval, sampler: &mut Sampler) -> f64 {
+        self.0.transmittance(r, ray_t, sampler)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        self.0.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut Sampler) -> Vec3 {
+        self.0.random(origin, time, sampler)
+    }
+
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        self.0.emitters()
+    }
+}
+
+/// Rotates an object about the world x axis by an angle in degrees.
+pub struct RotateX(Transform);
+
+impl RotateX {
+    pub fn new(object: Arc<dyn Hittable>, degrees: f64) -> Self {
+        RotateX(Transform::new(object, Mat4::rotation_x(degrees)).expect("rotations are invertible"))
+    }
+}
+
+impl Hittable for RotateX {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.0.hit(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.0.bounding_box()
+    }
+
+    fn shadow_hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.0.shadow_hit(r, ray_t)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval, sampler: &mut Sampler) -> f64 {
+        self.0.transmittance(r, ray_t, sampler)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        self.0.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut Sampler) -> Vec3 {
+        self.0.random(origin, time, sampler)
+    }
+
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        self.0.emitters()
+    }
+}
+
+/// Rotates an object about the world y axis by an angle in degrees.
+pub struct RotateY(Transform);
+
+impl RotateY {
+    pub fn new(object: Arc<dyn Hittable>, degrees: f64) -> Self {
+        RotateY(Transform::new(object, Mat4::rotation_y(degrees)).expect("rotations are invertible"))
+    }
+}
+
+impl Hittable for RotateY {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.0.hit(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.0.bounding_box()
+    }
+
+    fn shadow_hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.0.shadow_hit(r, ray_t)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval, sampler: &mut Sampler) -> f64 {
+        self.0.transmittance(r, ray_t, sampler)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        self.0.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut Sampler) -> Vec3 {
+        self.0.random(origin, time, sampler)
+    }
+
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        self.0.emitters()
+    }
+}
+
+/// Rotates an object about the world z axis by an angle in degrees.
+pub struct RotateZ(Transform);
+
+impl RotateZ {
+    pub fn new(object: Arc<dyn Hittable>, degrees: f64) -> Self {
+        RotateZ(Transform::new(object, Mat4::rotation_z(degrees)).expect("rotations are invertible"))
+    }
+}
+
+impl Hittable for RotateZ {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.0.hit(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.0.bounding_box()
+    }
+
+    fn shadow_hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.0.shadow_hit(r, ray_t)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval, sampler: &mut Sampler) -> f64 {
+        self.0.transmittance(r, ray_t, sampler)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        self.0.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut Sampler) -> Vec3 {
+        self.0.random(origin, time, sampler)
+    }
+
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        self.0.emitters()
+    }
+}
+
+/// Scales an object about the origin, independently along each axis.
+pub struct Scale(Transform);
+
+impl Scale {
+    /// # Panics
+    ///
+    /// Panics if any of the factors is zero.
+    pub fn new(object: Arc<dyn Hittable>, factors: Vec3) -> Self {
+        let transform = Transform::new(object, Mat4::scaling(factors));
+        Scale(transform.expect("scale factors must be non-zero"))
+    }
+}
+
+impl Hittable for Scale {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.0.hit(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.0.bounding_box()
+    }
+
+    fn shadow_hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.0.shadow_hit(r, ray_t)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval, sampler: &mut Sampler) -> f64 {
+        self.0.transmittance(r, ray_t, sampler)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        self.0.pdf_value(origin, direction, time)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut Sampler) -> Vec3 {
+        self.0.random(origin, time, sampler)
+    }
+
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        self.0.emitters()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::{DiffuseLight, Lambertian, Material};
+    use crate::quad::{make_box, Quad};
+    use crate::sphere::Sphere;
+
+    fn unit_sphere() -> Arc<dyn Hittable> {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, mat))
+    }
+
+    fn near(a: Vec3, b: Vec3) -> bool {
+        (a - b).length() < 1e-9
+    }
+
+    #[test]
+    fn non_uniform_scale_maps_normals_with_the_inverse_transpose() {
+        // An ellipsoid twice as wide as it is tall.
+        let ellipsoid = Scale::new(unit_sphere(), Vec3::new(2.0, 1.0, 1.0));
+        let bbox = ellipsoid.bounding_box();
+        assert_eq!((bbox.x.min, bbox.x.max, bbox.y.max), (-2.0, 2.0, 1.0));
+
+        // At (1, 1/2, sqrt(1/2)) the gradient of x^2/4 + y^2 + z^2 is
+        // (1/2, 1, sqrt(2)).
+        let target = Point3::new(1.0, 0.5, 0.5_f64.sqrt());
+        let r = Ray::new(Point3::new(1.0, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = ellipsoid.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+        assert!(near(rec.p, target));
+        assert!(rec.front_face);
+        assert!(near(rec.normal, unit_vector(Vec3::new(0.5, 1.0, 2.0_f64.sqrt()))));
+    }
+
+    #[test]
+    fn instances_share_geometry_and_move_bounding_boxes() {
+        let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let cube: Arc<dyn Hittable> = Arc::new(make_box(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 1.0),
+            mat,
+        ));
+
+        let rotated: Arc<dyn Hittable> = Arc::new(RotateY::new(cube.clone(), 45.0));
+        let moved = Translate::new(rotated, Vec3::new(10.0, 0.0, 0.0));
+        let bbox = moved.bounding_box();
+        let sqrt2 = 2.0_f64.sqrt();
+        // The quads' boxes are padded slightly, so compare loosely.
+        assert!((bbox.x.min - 10.0).abs() < 1e-3);
+        assert!((bbox.x.max - (10.0 + sqrt2)).abs() < 1e-3);
+        assert!((bbox.z.min + sqrt2 / 2.0).abs() < 1e-3);
+        assert_eq!(Arc::strong_count(&cube), 2);
+
+        // The cube's z = 1 face now faces +x and +z.
+        let r = Ray::new(Point3::new(11.0, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = moved.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+        assert!(near(rec.p, Point3::new(11.0, 0.5, sqrt2 - 1.0)));
+        assert!(near(rec.normal, unit_vector(Vec3::new(1.0, 0.0, 1.0))));
+
+        let flipped = RotateX::new(unit_sphere(), 180.0);
+        let tilted = RotateZ::new(unit_sphere(), 30.0);
+        assert!(near(Vec3::new(flipped.bounding_box().y.max, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)));
+        assert!(tilted.bounding_box().x.max > 1.0);
+    }
+
+    #[test]
+    fn instanced_lights_are_sampled_like_the_placed_shape() {
+        // A unit quad stretched, turned and moved is the quad with its
+        // corners mapped the same way, and must be sampled with the same
+        // density even though scaling changes solid angles.
+        let lamp: Arc<dyn Material> = Arc::new(DiffuseLight::new(Color::new(1.0, 1.0, 1.0)));
+        let q = Point3::new(0.0, 0.0, 0.0);
+        let (u, v) = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let panel = Arc::new(Quad::new(q, u, v, lamp.clone()));
+        let matrix = Mat4::translation(Vec3::new(0.5, 3.0, -1.0))
+            * Mat4::rotation_z(20.0)
+            * Mat4::scaling(Vec3::new(3.0, 1.0, 0.5));
+        let placed = Transform::new(panel, matrix).unwrap().emitters().unwrap();
+        let expected = Quad::new(
+            matrix.transform_point(q),
+            matrix.transform_vector(u),
+            matrix.transform_vector(v),
+            lamp,
+        );
+
+        let origin = Point3::new(0.3, 0.0, 0.2);
+        let mut sampler = Sampler::new(7);
+        for _ in 0..100 {
+            let direction = placed.random(origin, 0.0, &mut sampler);
+            let pdf = placed.pdf_value(origin, direction, 0.0);
+            assert!(pdf > 0.0);
+            assert!((pdf / expected.pdf_value(origin, direction, 0.0) - 1.0).abs() < 1e-9);
+        }
+        assert!(unit_sphere().emitters().is_none());
+    }
+
+    #[test]
+    fn singular_or_projective_matrices_are_rejected() {
+        assert!(Transform::new(unit_sphere(), Mat4::scaling(Vec3::new(1.0, 0.0, 1.0))).is_none());
+        let mut projective = Mat4::identity();
+        projective.m[3][2] = 1.0;
+        assert!(Transform::new(unit_sphere(), projective).is_none());
+    }
+}
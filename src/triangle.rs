@@ -0,0 +1,323 @@
+use crate::{
+    aabb::Aabb,
+    hit::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    sampler::Sampler,
+    vec3::{cross, dot, unit_vector, Point3, Vec3},
+};
+use std::sync::Arc;
+
+/// A triangle with optional per-vertex shading normals and texture
+/// coordinates, both interpolated with the barycentric coordinates of the
+/// hit point.
+///
+/// Intersection uses the watertight algorithm of Woop, Benthin and Wald
+/// (JCGT 2013): edge tests are evaluated in a ray-aligned space where a
+/// point on an edge shared by two triangles gets exactly the same edge
+/// function value in both, so rays can't slip between them.
+#[derive(Clone)]
+pub struct Triangle {
+    vertices: [Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: [(f64, f64); 3],
+    tangent: Vec3,
+    geometric_normal: Vec3,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Triangle {
+    pub fn new(p0: Point3, p1: Point3, p2: Point3, mat: Arc<dyn Material>) -> Self {
+        let bbox = Aabb::from_boxes(&Aabb::from_points(p0, p1), &Aabb::from_points(p1, p2));
+        Triangle {
+            vertices: [p0, p1, p2],
+            normals: None,
+            uvs: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            tangent: p1 - p0,
+            geometric_normal: unit_vector(cross(p1 - p0, p2 - p0)),
+            mat,
+            bbox,
+        }
+    }
+
+    /// Smooth shading normals at each vertex. They need not be unit length.
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    /// Texture coordinates at each vertex. Without them a hit reports the
+    /// barycentric weights of the second and third vertices as `(u, v)`.
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+        self.uvs = uvs;
+        self.tangent = uv_tangent(self.vertices, uvs);
+        self
+    }
+}
+
+/// The derivative of position with respect to `u` across the triangle, or
+/// its first edge if the texture coordinates are degenerate.
+fn uv_tangent([p0, p1, p2]: [Point3; 3], [uv0, uv1, uv2]: [(f64, f64); 3]) -> Vec3 {
+    let (e1, e2) = (p1 - p0, p2 - p0);
+    let (du1, dv1) = (uv1.0 - uv0.0, uv1.1 - uv0.1);
+    let (du2, dv2) = (uv2.0 - uv0.0, uv2.1 - uv0.1);
+    let det = du1 * dv2 - du2 * dv1;
+    if det.abs() < 1e-12 {
+        return e1;
+    }
+    (dv2 * e1 - dv1 * e2) / det
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let dir = r.direction();
+
+        // Permute axes so the ray travels mostly along the new z axis, and
+        // swap x and y when that would flip the winding.
+        let kz = max_dimension(dir);
+        let mut kx = (kz + 1) % 3;
+        let mut ky = (kx + 1) % 3;
+        if dir[kz] < 0.0 {
+            std::mem::swap(&mut kx, &mut ky);
+        }
+
+        // Shear so the ray direction becomes (0, 0, 1).
+        let sx = dir[kx] / dir[kz];
+        let sy = dir[ky] / dir[kz];
+        let sz = 1.0 / dir[kz];
+
+        let a = self.vertices[0] - r.origin();
+        let b = self.vertices[1] - r.origin();
+        let c = self.vertices[2] - r.origin();
+
+        let ax = a[kx] - sx * a[kz];
+        let ay = a[ky] - sy * a[kz];
+        let bx = b[kx] - sx * b[kz];
+        let by = b[ky] - sy * b[kz];
+        let cx = c[kx] - sx * c[kz];
+        let cy = c[ky] - sy * c[kz];
+
+        // Scaled barycentric coordinates; a hit needs them all on the same
+        // side, with zero counting as inside so shared edges are covered.
+        let u = cx * by - cy * bx;
+        let v = ax * cy - ay * cx;
+        let w = bx * ay - by * ax;
+        if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+            return None;
+        }
+
+        let det = u + v + w;
+        if det == 0.0 {
+            return None;
+        }
+
+        let az = sz * a[kz];
+        let bz = sz * b[kz];
+        let cz = sz * c[kz];
+        let t = (u * az + v * bz + w * cz) / det;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let b0 = u / det;
+        let b1 = v / det;
+        let b2 = w / det;
+
+        let p = self.vertices[0] * b0 + self.vertices[1] * b1 + self.vertices[2] * b2;
+        let tex_u = self.uvs[0].0 * b0 + self.uvs[1].0 * b1 + self.uvs[2].0 * b2;
+        let tex_v = self.uvs[0].1 * b0 + self.uvs[1].1 * b1 + self.uvs[2].1 * b2;
+
+        // Which side was hit comes from the true surface; the shading normal
+        // is then turned to that same side.
+        let front_face = dot(dir, self.geometric_normal) < 0.0;
+        let facing = if front_face {
+            self.geometric_normal
+        } else {
+            -self.geometric_normal
+        };
+        let normal = match self.normals {
+            Some([n0, n1, n2]) => {
+                let shading = n0 * b0 + n1 * b1 + n2 * b2;
+                if shading.near_zero() {
+                    facing
+                } else if dot(shading, facing) < 0.0 {
+                    -unit_vector(shading)
+                } else {
+                    unit_vector(shading)
+                }
+            }
+            None => facing,
+        };
+
+        Some(HitRecord {
+            p,
+            normal,
+            t,
+            u: tex_u,
+            v: tex_v,
+            tangent: self.tangent,
+            front_face,
+            mat: self.mat.clone(),
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    /// Points are sampled uniformly over the triangle's area, as for a
+    /// [`Quad`](crate::quad::Quad).
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        let r = Ray::with_time(origin, direction, time);
+        let Some(rec) = self.hit(&r, Interval::new(0.001, f64::INFINITY)) else {
+            return 0.0;
+        };
+
+        let [p0, p1, p2] = self.vertices;
+        let area = 0.5 * cross(p1 - p0, p2 - p0).length();
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = dot(direction, self.geometric_normal).abs() / direction.length();
+        distance_squared / (cosine * area)
+    }
+
+    fn random(&self, origin: Point3, _time: f64, sampler: &mut Sampler) -> Vec3 {
+        // Folding the unit square along its diagonal covers the triangle
+        // uniformly.
+        let (mut a, mut b) = (sampler.random(), sampler.random());
+        if a + b > 1.0 {
+            (a, b) = (1.0 - a, 1.0 - b);
+        }
+        let [p0, p1, p2] = self.vertices;
+        p0 + a * (p1 - p0) + b * (p2 - p0) - origin
+    }
+
+    fn emitters(&self) -> Option<Arc<dyn Hittable>> {
+        self.mat.is_emissive().then(|| Arc::new(self.clone()) as Arc<dyn Hittable>)
+    }
+}
+
+fn max_dimension(v: Vec3) -> usize {
+    let (x, y, z) = (v.x().abs(), v.y().abs(), v.z().abs());
+    if x > y {
+        if x > z { 0 } else { 2 }
+    } else if y > z {
+        1
+    } else {
+        2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+
+    fn matte() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    fn forward() -> Interval {
+        Interval::new(0.001, f64::INFINITY)
+    }
+
+    #[test]
+    fn interpolates_normals_and_uvs() {
+        let tri = Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            matte(),
+        )
+        .with_normals([
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(0.0, 1.0, 1.0),
+        ])
+        .with_uvs([(0.0, 0.0), (2.0, 0.0), (0.0, 4.0)]);
+
+        let r = Ray::new(Point3::new(0.25, 0.5, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = tri.hit(&r, forward()).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-12);
+        assert!((rec.u - 0.5).abs() < 1e-12 && (rec.v - 2.0).abs() < 1e-12);
+        assert!(rec.front_face);
+        let expected = unit_vector(Vec3::new(0.25, 0.5, 1.0));
+        assert!((rec.normal - expected).length() < 1e-12);
+
+        // From behind, both normals flip toward the viewer.
+        let r = Ray::new(Point3::new(0.25, 0.5, -3.0), Vec3::new(0.0, 0.0, 1.0));
+        let rec = tri.hit(&r, forward()).unwrap();
+        assert!(!rec.front_face);
+        assert!((rec.normal + expected).length() < 1e-12);
+    }
+
+    #[test]
+    fn misses_outside_and_parallel_rays() {
+        let tri = Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            matte(),
+        );
+        let outside = Ray::new(Point3::new(0.6, 0.6, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(tri.hit(&outside, forward()).is_none());
+        let parallel = Ray::new(Point3::new(-1.0, 0.2, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(tri.hit(&parallel, forward()).is_none());
+        let behind = Ray::new(Point3::new(0.2, 0.2, 1.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(tri.hit(&behind, forward()).is_none());
+    }
+
+    #[test]
+    fn rays_never_leak_through_shared_edges() {
+        // Two triangles sharing the diagonal of a skewed quad lying in a
+        // tilted plane.
+        let on_plane = |x: f64, y: f64| Point3::new(x, y, 0.3 * x - 0.2 * y);
+        let p0 = on_plane(-1.3, -0.7);
+        let p1 = on_plane(1.1, -0.9);
+        let p2 = on_plane(0.9, 1.2);
+        let p3 = on_plane(-0.8, 1.0);
+        let first = Triangle::new(p0, p1, p2, matte());
+        let second = Triangle::new(p0, p2, p3, matte());
+
+        let mut sampler = Sampler::new(11);
+        for _ in 0..20_000 {
+            // Aim at random points on the shared edge from random origins.
+            let s = sampler.random();
+            let target = p0 + (p2 - p0) * s;
+            let origin = Point3::new(
+                sampler.random_range(-3.0, 3.0),
+                sampler.random_range(-3.0, 3.0),
+                sampler.random_range(2.0, 4.0),
+            );
+            let r = Ray::new(origin, target - origin);
+            let hits = first.hit(&r, forward()).is_some() || second.hit(&r, forward()).is_some();
+            assert!(hits, "ray leaked through the shared edge at s = {s}");
+        }
+    }
+
+    #[test]
+    fn light_sampling_density_integrates_to_the_solid_angle() {
+        // Half of a 2x2 square one unit above the origin, cut along its
+        // diagonal, subtends half of the square's 2π/3 steradians.
+        let triangle = Triangle::new(
+            Point3::new(-1.0, 1.0, -1.0),
+            Point3::new(1.0, 1.0, -1.0),
+            Point3::new(-1.0, 1.0, 1.0),
+            matte(),
+        );
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let mut sampler = Sampler::new(11);
+        let n = 100_000;
+        let mut solid_angle = 0.0;
+        for _ in 0..n {
+            let pdf = triangle.pdf_value(origin, triangle.random(origin, 0.0, &mut sampler), 0.0);
+            assert!(pdf > 0.0);
+            solid_angle += 1.0 / pdf;
+        }
+        solid_angle /= n as f64;
+        assert!((solid_angle - std::f64::consts::PI / 3.0).abs() < 0.01, "{solid_angle}");
+        assert_eq!(triangle.pdf_value(origin, Vec3::new(1.0, 1.0, 1.0), 0.0), 0.0);
+    }
+}
@@ -1,5 +1,5 @@
-use rand::Rng;
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use crate::sampler::Sampler;
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub, SubAssign};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Vec3 {
@@ -33,21 +33,15 @@ impl Vec3 {
         self.length_squared().sqrt()
     }
 
-    pub fn random(&self) -> Vec3 {
-        let mut rng = rand::rng();
-        Vec3::new(
-            rng.random_range(0.0..1.0),
-            rng.random_range(0.0..1.0),
-            rng.random_range(0.0..1.0),
-        )
+    pub fn random(sampler: &mut Sampler) -> Vec3 {
+        Vec3::new(sampler.random(), sampler.random(), sampler.random())
     }
 
-    pub fn random_from_range(min: f64, max: f64) -> Vec3 {
-        let mut rng = rand::rng();
+    pub fn random_from_range(sampler: &mut Sampler, min: f64, max: f64) -> Vec3 {
         Vec3::new(
-            rng.random_range(min..max),
-            rng.random_range(min..max),
-            rng.random_range(min..max),
+            sampler.random_range(min, max),
+            sampler.random_range(min, max),
+            sampler.random_range(min, max),
         )
     }
 
@@ -57,6 +51,14 @@ impl Vec3 {
     }
 }
 
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, i: usize) -> &Self::Output {
+        &self.e[i]
+    }
+}
+
 impl Add for Vec3 {
     type Output = Self;
 
@@ -171,12 +173,11 @@ pub fn unit_vector(v: Vec3) -> Vec3 {
     v / v.length()
 }
 
-pub fn random_in_unit_disk() -> Vec3 {
+pub fn random_in_unit_disk(sampler: &mut Sampler) -> Vec3 {
     loop {
-        let mut rng = rand::rng();
         let p = Vec3::new(
-            rng.random_range(-1.0..1.0),
-            rng.random_range(-1.0..1.0),
+            sampler.random_range(-1.0, 1.0),
+            sampler.random_range(-1.0, 1.0),
             0.0,
         );
         if p.length_squared() <= 1.0 {
@@ -185,9 +186,9 @@ pub fn random_in_unit_disk() -> Vec3 {
     }
 }
 
-pub fn random_unit_vector() -> Vec3 {
+pub fn random_unit_vector(sampler: &mut Sampler) -> Vec3 {
     loop {
-        let p = Vec3::random_from_range(-1.0, 1.0);
+        let p = Vec3::random_from_range(sampler, -1.0, 1.0);
         let lensq = p.length_squared();
         if 1e-160 < lensq && lensq <= 1.0 {
             return p / lensq.sqrt();
@@ -195,8 +196,8 @@ pub fn random_unit_vector() -> Vec3 {
     }
 }
 
-pub fn random_on_hemisphere(normal: Vec3) -> Vec3 {
-    let mut on_unit_sphere = random_unit_vector();
+pub fn random_on_hemisphere(sampler: &mut Sampler, normal: Vec3) -> Vec3 {
+    let mut on_unit_sphere = random_unit_vector(sampler);
     if dot(normal, on_unit_sphere) < 0.0 {
         on_unit_sphere = -on_unit_sphere;
     }